@@ -1,80 +1,517 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
     path::Path,
 };
 
-use crate::visit::{self, Visitor};
-use anyhow::Result;
-use rustdoc_types::{Crate, Id, Import, Span};
+use crate::{
+    dependencies::{self, Dependency},
+    macros::MacroScanner,
+    reachability,
+    visit::{self, Exposure, Visitor},
+};
+use anyhow::{ensure, Context, Result};
+use clap::ValueEnum;
+use rustdoc_types::{Crate, Id, Import, ItemEnum, ItemKind, Span, FORMAT_VERSION};
+use semver::Version;
+use serde::Deserialize;
+
+/// How to treat public items marked `#[doc(hidden)]`.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Hidden {
+    /// Treat hidden items like any other public item.
+    #[default]
+    Include,
+    /// Ignore hidden items.
+    Exclude,
+    /// Report crates that are only exposed through hidden items separately.
+    Separate,
+}
 
 pub struct AnalyzeOutput {
     pub krate: Crate,
+    pub hidden: Hidden,
     pub crate_id_to_public_item: HashMap<u32, HashSet<Id>>,
-    pub id_to_usages: HashMap<Id, HashSet<Span>>,
+    pub id_to_usages: HashMap<Id, HashSet<Usage>>,
+    /// Public path of each reachable item in the crate.
+    pub public_paths: HashMap<Id, Vec<String>>,
+    /// The package each external crate comes from, if it could be found.
+    pub dependencies: HashMap<u32, Dependency>,
 }
 
-pub fn run(doc_json_path: &Path, include_std: bool) -> Result<AnalyzeOutput> {
-    let krate = serde_json::from_str::<Crate>(&std::fs::read_to_string(doc_json_path)?)?;
+/// An external crate, identified by name and, if it's known, version.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CrateRef {
+    /// The crate name rustdoc uses, with `-` replaced by `_`.
+    pub name: String,
+    pub version: Option<Version>,
+    /// Name of the package the crate comes from.
+    pub package: Option<String>,
+    /// Name of the dependency in Cargo.toml, if it's a direct dependency.
+    pub dependency_key: Option<String>,
+}
+
+impl CrateRef {
+    /// Whether `name` refers to the crate, by crate name, package name, or dependency key.
+    pub fn has_name(&self, name: &str) -> bool {
+        let name = name.replace('-', "_");
+        self.name == name
+            || self
+                .package
+                .as_ref()
+                .is_some_and(|package| package.replace('-', "_") == name)
+            || self.dependency_key.as_ref() == Some(&name)
+    }
+
+    /// Whether the crate is in the dependency graph but isn't a direct dependency, so users can't
+    /// name its items without adding it to their own dependencies.
+    pub fn is_transitive(&self) -> bool {
+        self.package.is_some() && self.dependency_key.is_none()
+    }
+}
+
+impl fmt::Display for CrateRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, " {version}")?;
+        }
+
+        let mut other_names = Vec::new();
+        if let Some(package) = &self.package {
+            if package.replace('-', "_") != self.name {
+                other_names.push(format!("package {package}"));
+            }
+        }
+        if let Some(dependency_key) = &self.dependency_key {
+            if *dependency_key != self.name {
+                other_names.push(format!("dependency {dependency_key}"));
+            }
+        }
+        if !other_names.is_empty() {
+            write!(f, " ({})", other_names.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A public item in the analyzed crate that uses an external item.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Usage {
+    pub item: Id,
+    pub span: Option<Span>,
+    /// Whether the usage comes from a trait impl, e.g. `impl serde::Serialize for MyType`, rather
+    /// than a signature.
+    pub trait_impl: bool,
+    /// Where in the item's signature the external item appears.
+    pub exposure: Exposure,
+    /// Whether the item is only reachable through `#[doc(hidden)]` items.
+    pub hidden: bool,
+}
+
+impl AnalyzeOutput {
+    /// Names of the external crates in the public API.
+    pub fn crates_in_public_api(&self) -> Result<BTreeSet<String>> {
+        self.crate_id_to_public_item
+            .keys()
+            .map(|crate_id| {
+                Ok(self
+                    .krate
+                    .external_crates
+                    .get(crate_id)
+                    .context("crate missing")?
+                    .name
+                    .replace('-', "_"))
+            })
+            .collect()
+    }
+
+    /// The name and version of an external crate.
+    ///
+    /// The version comes from `cargo metadata`, falling back to the crate's docs.rs URL.
+    pub fn crate_ref(&self, crate_id: u32) -> Result<CrateRef> {
+        let external_crate = self
+            .krate
+            .external_crates
+            .get(&crate_id)
+            .context("crate missing")?;
+        let dependency = self.dependencies.get(&crate_id);
+        let version = match dependency {
+            Some(dependency) => Some(dependency.version.clone()),
+            None => external_crate
+                .html_root_url
+                .as_deref()
+                .and_then(|url| dependencies::docs_rs_version(&external_crate.name, url))
+                .and_then(|version| version.parse().ok()),
+        };
+        Ok(CrateRef {
+            name: external_crate.name.replace('-', "_"),
+            version,
+            package: dependency.map(|dependency| dependency.name.clone()),
+            dependency_key: dependency.and_then(|dependency| dependency.dependency_key.clone()),
+        })
+    }
+
+    /// Paths of the public items from each external crate.
+    pub fn public_items(&self) -> Result<BTreeMap<CrateRef, BTreeSet<String>>> {
+        self.public_items_with_usage(|_, _| true)
+    }
+
+    /// Like [`AnalyzeOutput::public_items`] but only items with a usage that passes `filter`,
+    /// which is given the crate and the usage.
+    pub fn public_items_with_usage(
+        &self,
+        filter: impl Fn(&CrateRef, &Usage) -> bool,
+    ) -> Result<BTreeMap<CrateRef, BTreeSet<String>>> {
+        let mut public_items = BTreeMap::<CrateRef, BTreeSet<String>>::new();
+        for (crate_id, ids) in &self.crate_id_to_public_item {
+            let krate = self.crate_ref(*crate_id)?;
+            for id in ids {
+                let mut usages = self.id_to_usages.get(id).into_iter().flatten();
+                if !usages.any(|usage| filter(&krate, usage)) {
+                    continue;
+                }
+                let item = self.krate.paths.get(id).context("path missing")?;
+                public_items
+                    .entry(krate.clone())
+                    .or_default()
+                    .insert(item.path.join("::"));
+            }
+        }
+        Ok(public_items)
+    }
+
+    /// Paths of the modules that are re-exported in full, by `pub use dep;`,
+    /// `pub use dep::module::*;`, or `pub extern crate dep;`.
+    pub fn reexported_modules(&self) -> Result<BTreeMap<CrateRef, BTreeSet<String>>> {
+        let mut reexported_modules = BTreeMap::<CrateRef, BTreeSet<String>>::new();
+        for (crate_id, ids) in &self.crate_id_to_public_item {
+            let krate = self.crate_ref(*crate_id)?;
+            for id in ids {
+                let item = self.krate.paths.get(id).context("path missing")?;
+                if !matches!(item.kind, ItemKind::Module) {
+                    continue;
+                }
+                let reexported = self
+                    .id_to_usages
+                    .get(id)
+                    .into_iter()
+                    .flatten()
+                    .any(|usage| matches!(usage.exposure, Exposure::Import | Exposure::GlobImport));
+                if reexported {
+                    reexported_modules
+                        .entry(krate.clone())
+                        .or_default()
+                        .insert(item.path.join("::"));
+                }
+            }
+        }
+        Ok(reexported_modules)
+    }
+
+    /// Whether every item from the crate is only used by trait impls.
+    pub fn trait_impl_only(&self, crate_id: u32) -> bool {
+        self.all_usages(crate_id, |usage| usage.trait_impl)
+    }
+
+    /// Whether every item from the crate is only used by exported macros.
+    pub fn macro_only(&self, crate_id: u32) -> bool {
+        self.all_usages(crate_id, |usage| usage.exposure == Exposure::Macro)
+    }
+
+    /// Whether every item from the crate is only used by `#[doc(hidden)]` items.
+    pub fn hidden_only(&self, crate_id: u32) -> bool {
+        self.all_usages(crate_id, |usage| usage.hidden)
+    }
+
+    fn all_usages(&self, crate_id: u32, f: impl Fn(&Usage) -> bool) -> bool {
+        self.crate_id_to_public_item
+            .get(&crate_id)
+            .into_iter()
+            .flatten()
+            .flat_map(|id| self.id_to_usages.get(id).into_iter().flatten())
+            .all(f)
+    }
+
+    /// Describe an item in the analyzed crate, e.g. `fn mycrate::Client::send`.
+    pub fn describe_item(&self, id: &Id) -> String {
+        let path = self.public_path(id);
+        match self.krate.index.get(id).map(|item| &item.inner) {
+            Some(ItemEnum::Impl(impl_)) => match &impl_.trait_ {
+                Some(trait_) => {
+                    let trait_path = self
+                        .krate
+                        .paths
+                        .get(&trait_.id)
+                        .map_or_else(|| trait_.name.clone(), |item| item.path.join("::"));
+                    format!("impl {trait_path} for {path}")
+                }
+                None => format!("impl {path}"),
+            },
+            Some(ItemEnum::Import(import)) if import.glob => {
+                format!("use {}::* in {path}", import.source)
+            }
+            _ => format!("{} {path}", self.item_kind(id)),
+        }
+    }
+
+    /// Public path of an item in the analyzed crate, falling back to its name.
+    pub fn public_path(&self, id: &Id) -> String {
+        match self.public_paths.get(id) {
+            Some(path) => path.join("::"),
+            None => self
+                .krate
+                .index
+                .get(id)
+                .and_then(|item| item.name.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The keyword used to declare an item in the analyzed crate.
+    pub fn item_kind(&self, id: &Id) -> &'static str {
+        let Some(item) = self.krate.index.get(id) else { return "item" };
+        match &item.inner {
+            ItemEnum::Module(_) => "mod",
+            ItemEnum::ExternCrate { .. } => "extern crate",
+            ItemEnum::Import(_) => "use",
+            ItemEnum::Union(_) => "union",
+            ItemEnum::Struct(_) => "struct",
+            ItemEnum::StructField(_) => "field",
+            ItemEnum::Enum(_) => "enum",
+            ItemEnum::Variant(_) => "variant",
+            ItemEnum::Function(_) => "fn",
+            ItemEnum::Trait(_) | ItemEnum::TraitAlias(_) => "trait",
+            ItemEnum::Impl(_) => "impl",
+            ItemEnum::TypeAlias(_)
+            | ItemEnum::OpaqueTy(_)
+            | ItemEnum::ForeignType
+            | ItemEnum::AssocType { .. } => "type",
+            ItemEnum::Constant(_) | ItemEnum::AssocConst { .. } => "const",
+            ItemEnum::Static(_) => "static",
+            ItemEnum::Macro(_) | ItemEnum::ProcMacro(_) => "macro",
+            ItemEnum::Primitive(_) => "primitive",
+        }
+    }
+}
+
+/// Analyze the public API in the rustdoc JSON at `doc_json_path`.
+///
+/// `source_dir` is where the source files named in the docs are looked for, which is the
+/// directory of the package's Cargo.toml.
+pub fn run(
+    doc_json_path: &Path,
+    source_dir: &Path,
+    dependencies: &[Dependency],
+    include_std: bool,
+    hidden: Hidden,
+) -> Result<AnalyzeOutput> {
+    let krate = read(doc_json_path)?;
+    // only items that can be named from outside the crate are part of the public API
+    let reachable = reachability::run(&krate);
+    analyze(
+        krate,
+        reachable,
+        source_dir,
+        dependencies,
+        include_std,
+        hidden,
+    )
+}
+
+/// Like [`run`] but only analyzes the items at `paths`, such as `http::method::Method`, and the
+/// items reachable from them, rather than the whole public API.
+///
+/// Used for dependencies, of which users only see the parts that the analyzed crate exposes. Fails
+/// if none of `paths` are found, and warns about the ones that aren't, since the docs were likely
+/// built with different features than the analyzed crate was.
+pub fn run_from_items(
+    doc_json_path: &Path,
+    source_dir: &Path,
+    paths: &BTreeSet<String>,
+    dependencies: &[Dependency],
+    include_std: bool,
+    hidden: Hidden,
+) -> Result<AnalyzeOutput> {
+    let krate = read(doc_json_path)?;
+    let items = krate
+        .paths
+        .iter()
+        .filter(|(_, item)| item.crate_id == 0 && paths.contains(&item.path.join("::")))
+        .map(|(id, item)| (id.clone(), item.path.clone()))
+        .collect::<Vec<_>>();
+
+    let found = items
+        .iter()
+        .map(|(_, path)| path.join("::"))
+        .collect::<BTreeSet<_>>();
+    let missing = paths
+        .difference(&found)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    ensure!(
+        !found.is_empty(),
+        "none of the exposed items were found in {}: {missing}",
+        doc_json_path.display(),
+    );
+    if !missing.is_empty() {
+        eprintln!(
+            "warning: exposed items not found in {}: {missing}",
+            doc_json_path.display(),
+        );
+    }
+
+    let reachable = reachability::from_items(&krate, items);
+    analyze(
+        krate,
+        reachable,
+        source_dir,
+        dependencies,
+        include_std,
+        hidden,
+    )
+}
+
+/// Parse rustdoc JSON, checking the format version first since a mismatch otherwise shows up as
+/// an obscure parse error.
+fn read(doc_json_path: &Path) -> Result<Crate> {
+    #[derive(Deserialize)]
+    struct Header {
+        format_version: u32,
+    }
+
+    let json = std::fs::read_to_string(doc_json_path)
+        .with_context(|| format!("failed to read {}", doc_json_path.display()))?;
+    let header = serde_json::from_str::<Header>(&json)
+        .with_context(|| format!("failed to parse {}", doc_json_path.display()))?;
+    ensure!(
+        header.format_version == FORMAT_VERSION,
+        "{} has rustdoc JSON format version {}, but only version {FORMAT_VERSION} is supported. \
+        Build the docs with a toolchain that emits it using `--toolchain`",
+        doc_json_path.display(),
+        header.format_version,
+    );
+    serde_json::from_str(&json)
+        .with_context(|| format!("failed to parse {}", doc_json_path.display()))
+}
+
+fn analyze(
+    krate: Crate,
+    (public_paths, hidden_items): (HashMap<Id, Vec<String>>, HashSet<Id>),
+    source_dir: &Path,
+    dependencies: &[Dependency],
+    include_std: bool,
+    hidden: Hidden,
+) -> Result<AnalyzeOutput> {
+    let dependencies = dependencies::resolve(&krate, dependencies);
 
     let mut crate_id_to_public_item: HashMap<u32, HashSet<Id>> = <_>::default();
-    let mut id_to_usages: HashMap<Id, HashSet<Span>> = <_>::default();
+    let mut id_to_usages: HashMap<Id, HashSet<Usage>> = <_>::default();
+
+    let macros = MacroScanner::new(&krate, &public_paths, source_dir);
+
+    // trait impls and their associated items
+    let mut trait_impl_items = HashSet::new();
+    for id in public_paths.keys() {
+        if let ItemEnum::Impl(impl_) = &krate.index[id].inner {
+            if impl_.trait_.is_some() {
+                trait_impl_items.insert(id);
+                trait_impl_items.extend(&impl_.items);
+            }
+        }
+    }
 
-    for item in krate.index.values() {
-        // don't search through items defined in external crates
-        if krate.external_crates.contains_key(&item.crate_id) {
+    for id in public_paths.keys() {
+        if hidden == Hidden::Exclude && hidden_items.contains(id) {
             continue;
         }
 
+        let item = &krate.index[id];
+
         let mut item_visitor = ItemVisitor {
             krate: &krate,
             crate_id_to_public_item: <_>::default(),
             include_std,
+            macros: &macros,
         };
         visit::visit_item(item, &mut item_visitor);
 
         for (crate_id, ids) in item_visitor.crate_id_to_public_item {
-            if let Some(span) = &item.span {
-                for id in &ids {
-                    id_to_usages
-                        .entry(id.clone())
-                        .or_default()
-                        .insert(span.clone());
-                }
+            for (id, exposure) in &ids {
+                id_to_usages.entry(id.clone()).or_default().insert(Usage {
+                    item: item.id.clone(),
+                    span: item.span.clone(),
+                    trait_impl: trait_impl_items.contains(&item.id),
+                    exposure: *exposure,
+                    hidden: hidden_items.contains(&item.id),
+                });
             }
 
             crate_id_to_public_item
                 .entry(crate_id)
                 .or_default()
-                .extend(ids);
+                .extend(ids.into_iter().map(|(id, _)| id));
         }
     }
 
     Ok(AnalyzeOutput {
         krate,
+        hidden,
         crate_id_to_public_item,
         id_to_usages,
+        public_paths,
+        dependencies,
     })
 }
 
 struct ItemVisitor<'a> {
     krate: &'a Crate,
-    crate_id_to_public_item: HashMap<u32, HashSet<Id>>,
+    crate_id_to_public_item: HashMap<u32, HashSet<(Id, Exposure)>>,
     include_std: bool,
+    macros: &'a MacroScanner<'a>,
 }
 
 impl<'a> Visitor for ItemVisitor<'a> {
-    fn visit_path(&mut self, path: &rustdoc_types::Path) {
-        self.on_id(&path.id);
+    fn visit_path(&mut self, path: &rustdoc_types::Path, exposure: Exposure) {
+        self.on_id(&path.id, exposure);
     }
 
     fn visit_import(&mut self, import: &Import) {
         let Some(id) = &import.id else { return };
-        self.on_id(id);
+        if !import.glob {
+            self.on_id(id, Exposure::Import);
+            return;
+        }
+
+        // the items inside an external module aren't in this crate's docs, so only the module is
+        // recorded. `--transitive` follows it into the dependency's docs.
+        self.on_id(id, Exposure::GlobImport);
+    }
+
+    fn visit_extern_crate(&mut self, name: &str) {
+        // `pub extern crate dep;` re-exports the crate's root module
+        let root = self.krate.paths.iter().find(|(_, item)| {
+            matches!(item.kind, ItemKind::Module)
+                && item.path.len() == 1
+                && item.path[0] == name
+                && self.krate.external_crates.contains_key(&item.crate_id)
+        });
+        if let Some((id, _)) = root {
+            self.on_id(id, Exposure::Import);
+        }
+    }
+
+    fn visit_macro(&mut self, source: &str, span: Option<&Span>) {
+        for id in self.macros.external_items(source, span) {
+            self.on_id(id, Exposure::Macro);
+        }
     }
 }
 
 impl<'a> ItemVisitor<'a> {
-    fn on_id(&mut self, id: &Id) {
+    fn on_id(&mut self, id: &Id, exposure: Exposure) {
         let Some(item) = self.krate.paths.get(id) else { return };
         let Some(krate) = self.krate.external_crates.get(&item.crate_id) else { return };
 
@@ -87,6 +524,6 @@ impl<'a> ItemVisitor<'a> {
         self.crate_id_to_public_item
             .entry(item.crate_id)
             .or_default()
-            .insert(id.clone());
+            .insert((id.clone(), exposure));
     }
 }
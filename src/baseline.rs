@@ -0,0 +1,75 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+
+use crate::analyze::CrateRef;
+
+/// Items in a package's public API by the name of the crate they come from.
+pub type Items = BTreeMap<String, BTreeSet<String>>;
+
+const HEADER: &str = "\
+# Crates and items in the public API when the baseline was recorded. `cargo public-api-crates check
+# --baseline` only fails on items that aren't listed here. Update with `--fix`.
+";
+
+/// The items recorded for `package` in the baseline at `path`.
+pub fn read(path: &Path, package: &str) -> Result<Items> {
+    let mut packages = read_all(path).with_context(|| {
+        format!(
+            "failed to read baseline. Run `cargo public-api-crates check --baseline {} --fix` to \
+            create it",
+            path.display()
+        )
+    })?;
+    Ok(packages.remove(package).unwrap_or_default())
+}
+
+/// Record `public_items` as the baseline for `package`, keeping the baselines of other packages.
+pub fn write(
+    path: &Path,
+    package: &str,
+    public_items: &BTreeMap<CrateRef, BTreeSet<String>>,
+) -> Result<()> {
+    let mut packages = if path.exists() {
+        read_all(path)?
+    } else {
+        BTreeMap::new()
+    };
+
+    // several versions of a crate share an entry, the item paths don't include the version
+    let mut items = Items::new();
+    for (krate, paths) in public_items {
+        items
+            .entry(krate.name.clone())
+            .or_default()
+            .extend(paths.iter().cloned());
+    }
+    packages.insert(package.to_owned(), items);
+
+    let toml = toml::to_string_pretty(&packages).context("failed to serialize baseline")?;
+    std::fs::write(path, format!("{HEADER}\n{toml}"))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Baseline items that aren't in `public_items` anymore.
+pub fn stale(baseline: &Items, public_items: &BTreeMap<CrateRef, BTreeSet<String>>) -> Vec<String> {
+    baseline
+        .iter()
+        .flat_map(|(name, paths)| paths.iter().map(move |path| (name, path)))
+        .filter(|(name, path)| {
+            !public_items
+                .iter()
+                .any(|(krate, paths)| &krate.name == *name && paths.contains(*path))
+        })
+        .map(|(_, path)| path.clone())
+        .collect()
+}
+
+fn read_all(path: &Path) -> Result<BTreeMap<String, Items>> {
+    let toml = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    toml::from_str(&toml).with_context(|| format!("failed to parse {}", path.display()))
+}
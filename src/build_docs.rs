@@ -1,40 +1,192 @@
 use anyhow::{ensure, Context, Result};
-use glob::glob;
-use serde::Deserialize;
-use std::{path::PathBuf, process::Command};
+use clap::Args;
+use std::{
+    convert::Infallible,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use crate::find_and_parse_cargo_toml;
+use crate::{analyze::Hidden, dependencies::Dependency, workspace::Member};
 
-pub fn run(manifest_path: Option<PathBuf>, skip_build: bool) -> Result<PathBuf> {
-    let (manifest_path, toml) = find_and_parse_cargo_toml::<CargoToml>(manifest_path)?;
-    let package = toml.package.name.replace('-', "_");
+// Features to enable when building the docs. Forwarded to `cargo rustdoc`.
+#[derive(Args, Debug, Clone, Default)]
+pub struct Features {
+    /// Space or comma separated list of features to activate.
+    #[arg(short = 'F', long)]
+    pub features: Vec<String>,
 
-    if !skip_build {
-        let mut cmd = Command::new("cargo");
-        cmd.args(["+nightly", "rustdoc", "--all-features", "--manifest-path"]);
-        cmd.args([&manifest_path]);
-        cmd.args(["--", "-Z", "unstable-options", "--output-format", "json"]);
-        cmd.stdout(std::process::Stdio::null());
-        ensure!(cmd.spawn()?.wait()?.success(), "failed to build docs");
+    /// Activate all available features.
+    #[arg(long)]
+    pub all_features: bool,
+
+    /// Do not activate the `default` feature.
+    #[arg(long)]
+    pub no_default_features: bool,
+}
+
+impl Features {
+    fn apply(&self, cmd: &mut Command) {
+        let Self {
+            features,
+            all_features,
+            no_default_features,
+        } = self;
+        for features in features {
+            cmd.args(["--features", features]);
+        }
+        if *all_features {
+            cmd.arg("--all-features");
+        }
+        if *no_default_features {
+            cmd.arg("--no-default-features");
+        }
     }
+}
 
-    let mut entries = glob(&format!("target/**/doc/{package}.json"))
-        .context("failed to read glob pattern")?
-        .filter_map(Result::ok);
+/// The toolchain `cargo rustdoc` is run with. It has to emit the rustdoc JSON format version this
+/// tool understands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Toolchain {
+    /// `cargo +<toolchain>`, e.g. `nightly-2023-05-01`.
+    Named(String),
+    /// Plain `cargo`, which uses `rust-toolchain.toml` or the default toolchain. Written `none`.
+    Plain,
+}
 
-    let doc_json_path = entries
-        .next()
-        .with_context(|| format!("{package}.json file not found in target directory"))?;
+impl Default for Toolchain {
+    fn default() -> Self {
+        Self::Named("nightly".to_owned())
+    }
+}
 
-    Ok(doc_json_path)
+impl From<&str> for Toolchain {
+    fn from(toolchain: &str) -> Self {
+        match toolchain {
+            "none" => Self::Plain,
+            name => Self::Named(name.strip_prefix('+').unwrap_or(name).to_owned()),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
-struct CargoToml {
-    package: Package,
+impl FromStr for Toolchain {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+pub fn run(
+    member: &Member,
+    features: &Features,
+    hidden: Hidden,
+    skip_build: bool,
+) -> Result<PathBuf> {
+    let lib_name = member.lib_name.replace('-', "_");
+
+    if skip_build {
+        let doc_json_path = find_doc_json(&member.target_directory, &lib_name, UNIX_EPOCH)?;
+        return doc_json_path.with_context(|| {
+            format!(
+                "{lib_name}.json not found in {}, build the docs by running without \
+                `--skip-build`",
+                member.target_directory.display()
+            )
+        });
+    }
+
+    let mut cmd = cargo(member, "rustdoc");
+    features.apply(&mut cmd);
+    cmd.arg("--").args(rustdoc_args(hidden));
+    build(cmd, member, &member.name, &lib_name)
 }
 
-#[derive(Deserialize, Debug)]
-struct Package {
-    name: String,
+/// Build the docs of a package in `member`'s dependency graph, with the features that are
+/// enabled for it when building `member` with `features`.
+///
+/// `cargo rustdoc` doesn't accept features for packages outside the workspace, so the package is
+/// documented with `cargo doc` together with `member`, which the features apply to.
+pub fn run_dependency(
+    member: &Member,
+    dependency: &Dependency,
+    features: &Features,
+    hidden: Hidden,
+) -> Result<PathBuf> {
+    let spec = format!("{}@{}", dependency.name, dependency.version);
+    let mut cmd = cargo(member, "doc");
+    cmd.args(["--no-deps", "--package", &member.name, "--package", &spec]);
+    features.apply(&mut cmd);
+    cmd.env("RUSTDOCFLAGS", rustdoc_args(hidden).join(" "));
+    build(cmd, member, &spec, &dependency.lib_name)
+}
+
+/// Arguments that make rustdoc write JSON. rustdoc leaves out `#[doc(hidden)]` items unless it's
+/// told to document them, so they're documented unless they're excluded from the analysis.
+fn rustdoc_args(hidden: Hidden) -> Vec<&'static str> {
+    let mut args = vec!["-Z", "unstable-options", "--output-format", "json"];
+    if hidden != Hidden::Exclude {
+        args.push("--document-hidden-items");
+    }
+    args
+}
+
+/// `cargo [+toolchain] <subcommand> --manifest-path <manifest path>` for `member`.
+fn cargo(member: &Member, subcommand: &str) -> Command {
+    let mut cmd = Command::new("cargo");
+    if let Toolchain::Named(toolchain) = &member.toolchain {
+        cmd.arg(format!("+{toolchain}"));
+    }
+    cmd.args([subcommand, "--manifest-path"]);
+    cmd.arg(&member.manifest_path);
+    cmd
+}
+
+/// Run `cmd`, which builds the docs of `package`, and return the path of the JSON it wrote.
+fn build(mut cmd: Command, member: &Member, package: &str, lib_name: &str) -> Result<PathBuf> {
+    // rounded down since some file systems only store modification times in whole seconds
+    let build_started =
+        UNIX_EPOCH + Duration::from_secs(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
+
+    cmd.stdout(std::process::Stdio::null());
+    ensure!(
+        cmd.spawn()?.wait()?.success(),
+        "failed to build docs for `{package}`"
+    );
+
+    find_doc_json(&member.target_directory, lib_name, build_started)?.with_context(|| {
+        format!(
+            "{lib_name}.json wasn't written to {} by the build",
+            member.target_directory.display()
+        )
+    })
+}
+
+/// The most recently modified `<lib_name>.json` that was modified at or after `since`.
+///
+/// `cargo rustdoc` writes to `target/doc`, or to `target/<triple>/doc` when a target is set with
+/// `build.target` in `.cargo/config.toml` or `CARGO_BUILD_TARGET`, so both are searched.
+fn find_doc_json(
+    target_directory: &Path,
+    lib_name: &str,
+    since: SystemTime,
+) -> Result<Option<PathBuf>> {
+    let file_name = format!("{lib_name}.json");
+    let mut candidates = vec![target_directory.join("doc").join(&file_name)];
+    if let Ok(entries) = std::fs::read_dir(target_directory) {
+        for entry in entries {
+            candidates.push(entry?.path().join("doc").join(&file_name));
+        }
+    }
+
+    let newest = candidates
+        .into_iter()
+        .filter_map(|path| {
+            let modified = std::fs::metadata(&path).and_then(|metadata| metadata.modified());
+            Some((modified.ok()?, path))
+        })
+        .filter(|(modified, _)| *modified >= since)
+        .max_by_key(|(modified, _)| *modified);
+    Ok(newest.map(|(_, path)| path))
 }
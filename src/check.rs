@@ -1,76 +1,642 @@
-use std::{collections::BTreeSet, io::Write, path::PathBuf};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::PathBuf,
+};
 
-use crate::{analyze::AnalyzeOutput, find_and_parse_cargo_toml};
+use crate::{
+    analyze::{AnalyzeOutput, CrateRef, Hidden},
+    baseline, find_and_parse_cargo_toml, manifest,
+    workspace::Member,
+};
 use anyhow::{Context, Result};
+use clap::Args;
+use itertools::Itertools;
+use semver::{Version, VersionReq};
 use serde::Deserialize;
 
-pub fn run(manifest_path: Option<PathBuf>, analyze_output: AnalyzeOutput) -> Result<()> {
-    let AnalyzeOutput {
-        mut krate,
-        crate_id_to_public_item,
-        id_to_usages: _,
-    } = analyze_output;
-
-    let (_, toml) = find_and_parse_cargo_toml::<CargoToml>(manifest_path)?;
-    let allowed = toml
-        .package
-        .metadata
-        .cargo_public_api_crates
-        .allowed
-        .into_iter()
-        .map(|krate| krate.replace('-', "_"))
-        .collect::<BTreeSet<_>>();
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    /// Update the allow-list in Cargo.toml to match the crates in the public API.
+    #[arg(long)]
+    pub fix: bool,
 
-    let crates_in_public_api = crate_id_to_public_item
-        .into_keys()
-        .map(|crate_id| {
-            Ok(krate
-                .external_crates
-                .remove(&crate_id)
-                .context("crate missing")?
-                .name
-                .replace('-', "_"))
+    /// Check the dependencies marked `public = true` in Cargo.toml instead of the allow-list.
+    /// With `--fix`, dependencies are marked or unmarked to match the public API.
+    #[arg(long)]
+    pub public_deps: bool,
+
+    /// Only fail on items that aren't in this baseline file, and warn about baseline entries that
+    /// are no longer in the public API. With `--fix`, the baseline is written instead of updating
+    /// the allow-list.
+    #[arg(long, value_name = "FILE", conflicts_with = "public_deps")]
+    pub baseline: Option<PathBuf>,
+}
+
+/// Check the crates in `member`'s public API against its allow-list.
+///
+/// Returns whether the check passed. `show_package` prefixes the report with the package name,
+/// which is used when checking several workspace members.
+pub fn run(
+    member: &Member,
+    analyze_output: AnalyzeOutput,
+    args: &CheckArgs,
+    show_package: bool,
+) -> Result<bool> {
+    let CheckArgs {
+        fix,
+        public_deps,
+        baseline,
+    } = args;
+
+    let prefix = if show_package {
+        format!("{}: ", member.name)
+    } else {
+        String::new()
+    };
+
+    if *public_deps {
+        return check_public_deps(member, analyze_output, *fix, &prefix);
+    }
+
+    if let (Some(path), true) = (baseline, fix) {
+        baseline::write(path, &member.name, &analyze_output.public_items()?)?;
+        println!("{prefix}Wrote baseline to {}", path.display());
+        return Ok(true);
+    }
+    let baseline = baseline
+        .as_ref()
+        .map(|path| baseline::read(path, &member.name))
+        .transpose()?
+        .unwrap_or_default();
+    let in_baseline = |krate: &CrateRef, path: &String| {
+        baseline
+            .get(&krate.name)
+            .is_some_and(|paths| paths.contains(path))
+    };
+
+    let (_, toml) = find_and_parse_cargo_toml::<CargoToml>(Some(member.manifest_path.clone()))?;
+    let meta = match toml.package.metadata.cargo_public_api_crates {
+        Some(meta) => meta,
+        None if *fix => CargoPublicApiCratesMeta::default(),
+        None => {
+            eprintln!(
+                "note: {} has no `[package.metadata.cargo-public-api-crates]` table, so no crates \
+                are allowed. Run `cargo public-api-crates init` to create it.",
+                member.manifest_path.display()
+            );
+            CargoPublicApiCratesMeta::default()
+        }
+    };
+    let entries = allowed_entries(&meta.allowed)?;
+    let (allowed_items, allowed_crates): (Vec<_>, Vec<_>) = entries
+        .iter()
+        .partition(|entry| entry.pattern.contains("::"));
+    let allowed_trait_impls = normalize(&meta.allowed_trait_impls);
+    // hidden items only get their own allow-list when they're reported separately
+    let separate_hidden = analyze_output.hidden == Hidden::Separate;
+    let allowed_hidden = if separate_hidden {
+        normalize(&meta.allowed_hidden)
+    } else {
+        BTreeSet::new()
+    };
+
+    let public_items = analyze_output.public_items()?;
+    let visible_items = analyze_output.public_items_with_usage(|_, usage| !usage.hidden)?;
+    // usages in trait impls and hidden items are fine if the crate is allowed there, everything
+    // else has to be allowed as usual
+    let mut items_to_check = analyze_output.public_items_with_usage(|krate, usage| {
+        let allowed_here = (usage.trait_impl && has_any_name(krate, &allowed_trait_impls))
+            || (usage.hidden && has_any_name(krate, &allowed_hidden));
+        !allowed_here
+    })?;
+    // only exposures that are new since the baseline was recorded fail the check
+    for (krate, paths) in &mut items_to_check {
+        paths.retain(|path| !in_baseline(krate, path));
+    }
+    items_to_check.retain(|_, paths| !paths.is_empty());
+    let reexported_modules = analyze_output.reexported_modules()?;
+
+    let mut in_api_but_not_allowed = BTreeSet::new();
+    let mut in_hidden_items_but_not_allowed = BTreeSet::new();
+    let mut in_signatures_but_only_trait_impls_allowed = BTreeSet::new();
+    let mut in_visible_items_but_only_hidden_allowed = BTreeSet::new();
+    let mut items_in_api_but_not_allowed = BTreeSet::new();
+    let mut allowed_but_not_in_api = BTreeSet::new();
+    let mut items_allowed_but_not_in_api = BTreeSet::new();
+    let mut suggested_paths = BTreeMap::new();
+    let mut trait_impls_allowed_but_not_in_api = BTreeSet::new();
+    let mut hidden_allowed_but_not_in_api = BTreeSet::new();
+
+    // these can't be fixed by allowing them, users would have to add the dependency themselves to
+    // name the items
+    let not_direct_dependencies = public_items
+        .iter()
+        .filter(|(krate, paths)| {
+            krate.is_transitive() && !paths.iter().all(|path| in_baseline(krate, path))
         })
-        .collect::<Result<BTreeSet<_>>>()?;
+        .map(|(krate, _)| krate.clone())
+        .collect::<BTreeSet<_>>();
+
+    for (krate, paths) in &items_to_check {
+        if allowed_crates.iter().any(|entry| entry.allows_crate(krate)) {
+            continue;
+        }
 
-    let mut in_api_but_not_allowed = Vec::new();
-    let mut allowed_but_not_in_api = Vec::new();
+        let patterns = allowed_items
+            .iter()
+            .filter(|entry| entry.allows_crate(krate))
+            .collect::<Vec<_>>();
+        if patterns.is_empty() {
+            if has_any_name(krate, &allowed_trait_impls) {
+                in_signatures_but_only_trait_impls_allowed.insert(krate.clone());
+            } else if has_any_name(krate, &allowed_hidden) {
+                in_visible_items_but_only_hidden_allowed.insert(krate.clone());
+            } else if separate_hidden && !visible_items.contains_key(krate) {
+                in_hidden_items_but_not_allowed.insert(krate.clone());
+            } else {
+                in_api_but_not_allowed.insert(krate.clone());
+            }
+            continue;
+        }
 
-    for krate in &crates_in_public_api {
-        if !allowed.contains(krate) {
-            in_api_but_not_allowed.push(krate);
+        for path in paths {
+            if !patterns.iter().any(|entry| entry.matches(krate, path)) {
+                items_in_api_but_not_allowed.insert((krate.clone(), path.clone()));
+            }
         }
     }
 
-    for krate in allowed {
-        if !crates_in_public_api.contains(&krate) {
-            allowed_but_not_in_api.push(krate);
+    for entry in allowed_crates {
+        if !public_items.keys().any(|krate| entry.allows_crate(krate)) {
+            allowed_but_not_in_api.insert(entry.written.clone());
         }
     }
 
-    let status = if in_api_but_not_allowed.is_empty() && allowed_but_not_in_api.is_empty() {
-        0
-    } else {
-        1
+    for entry in allowed_items {
+        let crate_paths = public_items
+            .iter()
+            .filter(|(krate, _)| entry.allows_crate(krate));
+        let used = crate_paths
+            .clone()
+            .any(|(krate, paths)| paths.iter().any(|path| entry.matches(krate, path)));
+        if !used {
+            items_allowed_but_not_in_api.insert(entry.written.clone());
+            // the pattern most likely names the item where it's re-exported
+            let suggestions = crate_paths
+                .flat_map(|(_, paths)| suggestions(&entry.pattern, paths))
+                .collect::<BTreeSet<_>>();
+            if !suggestions.is_empty() {
+                suggested_paths.insert(entry.written.clone(), suggestions);
+            }
+        }
+    }
+
+    let in_api = |name: &String| public_items.keys().any(|krate| krate.has_name(name));
+    for krate in allowed_trait_impls.iter().filter(|krate| !in_api(krate)) {
+        trait_impls_allowed_but_not_in_api.insert(krate.clone());
+    }
+    for krate in allowed_hidden.iter().filter(|krate| !in_api(krate)) {
+        hidden_allowed_but_not_in_api.insert(krate.clone());
+    }
+
+    let mut stdout = std::io::stdout().lock();
+
+    let describe_crates = |crates: BTreeSet<CrateRef>| {
+        crates
+            .into_iter()
+            .map(|krate| match reexported_modules.get(&krate) {
+                Some(modules) => format!(
+                    "{krate} (re-exported in full: {})",
+                    modules.iter().join(", ")
+                ),
+                None => krate.to_string(),
+            })
+            .collect::<Vec<_>>()
     };
 
+    if *fix {
+        // entries for the crates and items that weren't allowed, with a version requirement if
+        // the allow-list has them
+        let mut add = BTreeMap::<String, String>::new();
+        let not_allowed = in_api_but_not_allowed
+            .iter()
+            .chain(&in_signatures_but_only_trait_impls_allowed)
+            .chain(&in_visible_items_but_only_hidden_allowed)
+            .map(|krate| (krate, &krate.name))
+            .chain(
+                items_in_api_but_not_allowed
+                    .iter()
+                    .map(|(krate, path)| (krate, path)),
+            );
+        for (krate, entry) in not_allowed {
+            let req = krate
+                .version
+                .as_ref()
+                .map_or("*".to_owned(), compatible_req);
+            add.entry(entry.clone())
+                .and_modify(|existing| {
+                    // several versions of the crate aren't allowed
+                    if *existing != req {
+                        *existing = "*".to_owned();
+                    }
+                })
+                .or_insert(req);
+        }
+        let remove = allowed_but_not_in_api
+            .into_iter()
+            .chain(items_allowed_but_not_in_api)
+            .collect::<BTreeSet<_>>();
+
+        match &meta.allowed {
+            Allowed::List(_) => {
+                let add = add.keys().cloned().collect();
+                manifest::update_list(&member.manifest_path, "allowed", &add, &remove)?;
+            }
+            Allowed::Table(_) => {
+                manifest::update_table(&member.manifest_path, "allowed", &add, &remove)?;
+            }
+        }
+
+        let remove_trait_impls = as_written(
+            &meta.allowed_trait_impls,
+            &trait_impls_allowed_but_not_in_api,
+        );
+        manifest::update_list(
+            &member.manifest_path,
+            "allowed-trait-impls",
+            &BTreeSet::new(),
+            &remove_trait_impls,
+        )?;
+
+        let add_hidden = in_hidden_items_but_not_allowed
+            .iter()
+            .map(|krate| krate.name.clone())
+            .collect();
+        let remove_hidden = as_written(&meta.allowed_hidden, &hidden_allowed_but_not_in_api);
+        manifest::update_list(
+            &member.manifest_path,
+            "allowed-hidden",
+            &add_hidden,
+            &remove_hidden,
+        )?;
+
+        for entry in add.keys().chain(&add_hidden) {
+            writeln!(&mut stdout, "{prefix}Allowed `{entry}`")?;
+        }
+        for entry in remove
+            .iter()
+            .chain(&remove_trait_impls)
+            .chain(&remove_hidden)
+        {
+            writeln!(&mut stdout, "{prefix}Removed `{entry}` from allow-list")?;
+        }
+
+        // allowing these doesn't help, so they still fail
+        if !not_direct_dependencies.is_empty() {
+            writeln!(
+                &mut stdout,
+                "{prefix}Crates in public API that aren't direct dependencies:"
+            )?;
+            for entry in describe_crates(not_direct_dependencies) {
+                writeln!(&mut stdout, "    {entry}")?;
+            }
+            return Ok(false);
+        }
+
+        return Ok(true);
+    }
+
+    let sections = [
+        (
+            "Crates in public API that weren't allowed:",
+            describe_crates(in_api_but_not_allowed),
+        ),
+        (
+            "Crates in public API that aren't direct dependencies:",
+            describe_crates(not_direct_dependencies),
+        ),
+        (
+            "Crates in #[doc(hidden)] items that weren't allowed:",
+            describe_crates(in_hidden_items_but_not_allowed),
+        ),
+        (
+            "Crates allowed in trait impls that are used in signatures:",
+            describe_crates(in_signatures_but_only_trait_impls_allowed),
+        ),
+        (
+            "Crates allowed in #[doc(hidden)] items that are used in other items:",
+            describe_crates(in_visible_items_but_only_hidden_allowed),
+        ),
+        (
+            "Items in public API that weren't allowed, by the path they're defined at:",
+            items_in_api_but_not_allowed
+                .into_iter()
+                .map(|(_, path)| path)
+                .collect(),
+        ),
+        (
+            "Crates that were allowed but weren't in public API:",
+            allowed_but_not_in_api.into_iter().collect(),
+        ),
+        (
+            "Items that were allowed but weren't in public API:",
+            items_allowed_but_not_in_api
+                .into_iter()
+                .map(|entry| match suggested_paths.get(&entry) {
+                    Some(paths) => {
+                        format!("{entry} (did you mean {}?)", paths.iter().join(" or "))
+                    }
+                    None => entry,
+                })
+                .collect(),
+        ),
+        (
+            "Crates that were allowed in trait impls but weren't in public API:",
+            trait_impls_allowed_but_not_in_api.into_iter().collect(),
+        ),
+        (
+            "Crates that were allowed in #[doc(hidden)] items but weren't in public API:",
+            hidden_allowed_but_not_in_api.into_iter().collect(),
+        ),
+    ];
+
+    let mut success = true;
+    for (title, entries) in sections {
+        if entries.is_empty() {
+            continue;
+        }
+        success = false;
+        writeln!(&mut stdout, "{prefix}{title}")?;
+        for entry in entries {
+            writeln!(&mut stdout, "    {entry}")?;
+        }
+    }
+
+    // the baseline can be tightened, but that's no reason to fail
+    let stale_baseline = baseline::stale(&baseline, &public_items);
+    if !stale_baseline.is_empty() {
+        writeln!(
+            &mut stdout,
+            "{prefix}Items in baseline that are no longer in public API (run with `--fix` to \
+            update it):"
+        )?;
+        for entry in stale_baseline {
+            writeln!(&mut stdout, "    {entry}")?;
+        }
+    }
+
+    Ok(success)
+}
+
+/// Check the crates in `member`'s public API against the dependencies in Cargo.toml marked
+/// `public = true`.
+fn check_public_deps(
+    member: &Member,
+    analyze_output: AnalyzeOutput,
+    fix: bool,
+    prefix: &str,
+) -> Result<bool> {
+    let dependencies = manifest::dependencies(&member.manifest_path)?;
+    let crates_in_api = analyze_output
+        .public_items()?
+        .into_keys()
+        .collect::<Vec<_>>();
+
+    let mut in_api_but_not_public = BTreeSet::new();
+    let mut in_api_but_not_dependencies = BTreeSet::new();
+    let mut public_but_not_in_api = BTreeSet::new();
+
+    for krate in &crates_in_api {
+        let dependency = dependencies
+            .iter()
+            .find(|(dependency, _)| krate.has_name(dependency));
+        match dependency {
+            Some((_, true)) => {}
+            Some((dependency, false)) => {
+                in_api_but_not_public.insert(dependency.clone());
+            }
+            None => {
+                in_api_but_not_dependencies.insert(krate.to_string());
+            }
+        }
+    }
+
+    for (dependency, public) in &dependencies {
+        if *public && !crates_in_api.iter().any(|krate| krate.has_name(dependency)) {
+            public_but_not_in_api.insert(dependency.clone());
+        }
+    }
+
     let mut stdout = std::io::stdout().lock();
 
-    if !in_api_but_not_allowed.is_empty() {
-        println!("Crates in public API that weren't allowed:");
-        for krate in in_api_but_not_allowed {
-            writeln!(&mut stdout, "    {krate}")?;
+    if fix {
+        manifest::set_public(
+            &member.manifest_path,
+            &in_api_but_not_public,
+            &public_but_not_in_api,
+        )?;
+
+        for dependency in &in_api_but_not_public {
+            writeln!(
+                &mut stdout,
+                "{prefix}Marked `{dependency}` as `public = true`"
+            )?;
         }
+        for dependency in &public_but_not_in_api {
+            writeln!(
+                &mut stdout,
+                "{prefix}Removed `public = true` from `{dependency}`"
+            )?;
+        }
+
+        return Ok(in_api_but_not_dependencies.is_empty());
     }
 
-    if !allowed_but_not_in_api.is_empty() {
-        println!("Crates that were allowed but weren't in public API:");
-        for krate in allowed_but_not_in_api {
-            writeln!(&mut stdout, "    {krate}")?;
+    let sections = [
+        (
+            "Dependencies in public API that aren't marked `public = true`:",
+            in_api_but_not_public,
+        ),
+        (
+            "Crates in public API that aren't direct dependencies:",
+            in_api_but_not_dependencies,
+        ),
+        (
+            "Dependencies marked `public = true` that aren't in public API:",
+            public_but_not_in_api,
+        ),
+    ];
+
+    let mut success = true;
+    for (title, entries) in sections {
+        if entries.is_empty() {
+            continue;
+        }
+        success = false;
+        writeln!(&mut stdout, "{prefix}{title}")?;
+        for entry in entries {
+            writeln!(&mut stdout, "    {entry}")?;
         }
     }
 
-    std::process::exit(status)
+    Ok(success)
+}
+
+/// An entry in `allowed`.
+struct AllowedEntry {
+    /// The entry as written in Cargo.toml.
+    written: String,
+    /// The crate name or item pattern, with `-` replaced by `_`. The crate can be named by its
+    /// crate name, package name, or dependency key.
+    pattern: String,
+    version_req: Option<VersionReq>,
+}
+
+impl AllowedEntry {
+    /// Whether the entry is for `krate`. Crates whose version isn't known match any requirement.
+    fn allows_crate(&self, krate: &CrateRef) -> bool {
+        let version_matches = match (&self.version_req, &krate.version) {
+            (Some(req), Some(version)) => req.matches(version),
+            _ => true,
+        };
+        krate.has_name(crate_name(&self.pattern)) && version_matches
+    }
+
+    /// Whether the entry is an item pattern that matches an item from `krate`.
+    fn matches(&self, krate: &CrateRef, path: &str) -> bool {
+        // item paths start with the crate name, which may not be how the pattern names the crate
+        let Some((_, rest)) = self.pattern.split_once("::") else {
+            return false;
+        };
+        matches(&format!("{}::{rest}", krate.name), path)
+    }
+}
+
+fn has_any_name(krate: &CrateRef, names: &BTreeSet<String>) -> bool {
+    names.iter().any(|name| krate.has_name(name))
+}
+
+fn allowed_entries(allowed: &Allowed) -> Result<Vec<AllowedEntry>> {
+    match allowed {
+        Allowed::List(entries) => Ok(entries
+            .iter()
+            .map(|entry| AllowedEntry {
+                written: entry.clone(),
+                pattern: entry.replace('-', "_"),
+                version_req: None,
+            })
+            .collect()),
+        Allowed::Table(entries) => entries
+            .iter()
+            .map(|(entry, req)| {
+                let version_req = req.parse().with_context(|| {
+                    format!("invalid version requirement `{req}` for `{entry}` in allow-list")
+                })?;
+                Ok(AllowedEntry {
+                    written: entry.clone(),
+                    pattern: entry.replace('-', "_"),
+                    version_req: Some(version_req),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// The requirement Cargo would use for `version`, e.g. `1` for `1.2.3` and `0.2` for `0.2.9`.
+pub fn compatible_req(version: &Version) -> String {
+    match (version.major, version.minor) {
+        (0, 0) => format!("0.0.{}", version.patch),
+        (0, minor) => format!("0.{minor}"),
+        (major, _) => major.to_string(),
+    }
+}
+
+fn normalize(entries: &BTreeSet<String>) -> BTreeSet<String> {
+    entries
+        .iter()
+        .map(|entry| entry.replace('-', "_"))
+        .collect()
+}
+
+/// The entries in `list`, as written in Cargo.toml, that normalize to one of `normalized`.
+fn as_written(list: &BTreeSet<String>, normalized: &BTreeSet<String>) -> BTreeSet<String> {
+    list.iter()
+        .filter(|entry| normalized.contains(&entry.replace('-', "_")))
+        .cloned()
+        .collect()
+}
+
+/// The crate an allow-list entry refers to. `http::request::*` refers to `http`.
+fn crate_name(entry: &str) -> &str {
+    entry.split("::").next().unwrap_or(entry)
+}
+
+/// Whether an item pattern from the allow-list matches the path of an item.
+///
+/// Items are named by the path they're defined at, as rustdoc records it, rather than where
+/// they're re-exported, so `http::HeaderMap` is `http::header::map::HeaderMap`. That pattern only
+/// matches itself, `http::request::*` matches everything under `http::request`.
+fn matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix("::*") {
+        Some(module) => path
+            .strip_prefix(module)
+            .is_some_and(|rest| rest.starts_with("::")),
+        None => pattern == path,
+    }
+}
+
+/// Paths of items that an item pattern which matched nothing was probably meant to be, because
+/// their last segment is the same, e.g. `http::header::map::HeaderMap` for `http::HeaderMap`. For
+/// a glob pattern the modules with the same name are suggested instead.
+fn suggestions(pattern: &str, paths: &BTreeSet<String>) -> BTreeSet<String> {
+    let (name, glob) = match pattern.strip_suffix("::*") {
+        Some(module) => (module, true),
+        None => (pattern, false),
+    };
+    let Some((_, name)) = name.rsplit_once("::") else { return BTreeSet::new() };
+
+    paths
+        .iter()
+        .filter_map(|path| {
+            let segments = path.split("::").collect::<Vec<_>>();
+            if glob {
+                // the crate name and the item itself aren't modules
+                let modules = segments.get(1..segments.len() - 1)?;
+                let position = modules.iter().position(|segment| *segment == name)?;
+                Some(format!("{}::*", segments[..position + 2].join("::")))
+            } else {
+                (segments.last() == Some(&name)).then(|| path.clone())
+            }
+        })
+        .collect()
+}
+
+/// Create `[package.metadata.cargo-public-api-crates]` in `member`'s manifest, allowing the crates
+/// currently in the public API.
+pub fn init(member: &Member, analyze_output: AnalyzeOutput) -> Result<()> {
+    let manifest_path = &member.manifest_path;
+
+    if manifest::has_table(manifest_path)? {
+        eprintln!(
+            "{} already has a `[package.metadata.cargo-public-api-crates]` table, use \
+            `check --fix` to update it",
+            manifest_path.display()
+        );
+        return Ok(());
+    }
+
+    let crates_in_public_api = analyze_output.crates_in_public_api()?;
+    // the table is created even if there's nothing to allow, so `check` knows `init` was run
+    manifest::create_list(manifest_path, "allowed", &crates_in_public_api)?;
+
+    println!(
+        "Allowed {} crate(s) in {}",
+        crates_in_public_api.len(),
+        manifest_path.display()
+    );
+
+    Ok(())
 }
 
 #[derive(Deserialize, Debug)]
@@ -80,16 +646,99 @@ struct CargoToml {
 
 #[derive(Deserialize, Debug)]
 struct Package {
+    #[serde(default)]
     metadata: Metadata,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
 #[serde(rename_all = "kebab-case")]
 struct Metadata {
-    cargo_public_api_crates: CargoPublicApiCratesMeta,
+    cargo_public_api_crates: Option<CargoPublicApiCratesMeta>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case")]
 struct CargoPublicApiCratesMeta {
-    allowed: BTreeSet<String>,
+    #[serde(default)]
+    allowed: Allowed,
+    /// Crates that may only appear in the public API through trait impls.
+    #[serde(default)]
+    allowed_trait_impls: BTreeSet<String>,
+    /// Crates that may only appear in the public API through `#[doc(hidden)]` items, used with
+    /// `--hidden separate`.
+    #[serde(default)]
+    allowed_hidden: BTreeSet<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Allowed {
+    /// `allowed = ["http", "serde::*"]`, which allows any version.
+    List(BTreeSet<String>),
+    /// `allowed = { http = "1" }`, which allows versions matching the requirement.
+    Table(BTreeMap<String, String>),
+}
+
+impl Default for Allowed {
+    fn default() -> Self {
+        Allowed::List(BTreeSet::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_pattern() {
+        let pattern = "http::header::map::HeaderMap";
+        assert!(matches(pattern, "http::header::map::HeaderMap"));
+        assert!(!matches(pattern, "http::header::map::HeaderMapX"));
+        assert!(!matches(pattern, "http::header::map::HeaderMap::new"));
+    }
+
+    #[test]
+    fn glob_pattern() {
+        let pattern = "http::request::*";
+        assert!(matches(pattern, "http::request::Request"));
+        assert!(matches(pattern, "http::request::builder::Builder"));
+        assert!(!matches(pattern, "http::request"));
+        assert!(!matches(pattern, "http::requestx::Foo"));
+    }
+
+    #[test]
+    fn pattern_names_crate_by_package() {
+        let entry = AllowedEntry {
+            written: "serde-json::value::*".to_owned(),
+            pattern: "serde_json::value::*".to_owned(),
+            version_req: None,
+        };
+        let krate = CrateRef {
+            name: "serde_json".to_owned(),
+            version: None,
+            package: Some("serde-json".to_owned()),
+            dependency_key: Some("serde_json".to_owned()),
+        };
+        assert!(entry.matches(&krate, "serde_json::value::Value"));
+        assert!(!entry.matches(&krate, "serde_json::map::Map"));
+    }
+
+    #[test]
+    fn suggest_definition_paths() {
+        let paths = BTreeSet::from([
+            "http::header::map::HeaderMap".to_owned(),
+            "http::request::Request".to_owned(),
+            "http::request::builder::Builder".to_owned(),
+        ]);
+        assert_eq!(
+            suggestions("http::HeaderMap", &paths),
+            BTreeSet::from(["http::header::map::HeaderMap".to_owned()])
+        );
+        assert_eq!(
+            suggestions("http::builder::*", &paths),
+            BTreeSet::from(["http::request::builder::*".to_owned()])
+        );
+        assert!(suggestions("http::Response", &paths).is_empty());
+        assert!(suggestions("http::Request::*", &paths).is_empty());
+    }
 }
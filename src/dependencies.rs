@@ -0,0 +1,108 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rustdoc_types::Crate;
+use semver::Version;
+
+use crate::metadata::Metadata;
+
+/// A package in a member's dependency graph.
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    /// Name of the package, which differs from `lib_name` for packages like `serde-json`.
+    pub name: String,
+    /// Name of the library target, which is what rustdoc calls the crate.
+    pub lib_name: String,
+    pub version: Version,
+    /// Where the package comes from, e.g. `registry+https://github.com/rust-lang/crates.io-index`.
+    /// `None` for path dependencies.
+    pub source: Option<String>,
+    /// Name the member uses for the dependency if it depends on it directly, which differs from
+    /// `lib_name` for renamed dependencies like `http1 = { package = "http", version = "1" }`.
+    pub dependency_key: Option<String>,
+}
+
+/// The packages that `package_id` depends on, directly or indirectly, through normal
+/// dependencies.
+pub fn closure(metadata: &Metadata, package_id: &str) -> Vec<Dependency> {
+    let Some(resolve) = &metadata.resolve else { return Vec::new() };
+    let nodes = resolve
+        .nodes
+        .iter()
+        .map(|node| (node.id.as_str(), node))
+        .collect::<HashMap<_, _>>();
+    let packages = metadata
+        .packages
+        .iter()
+        .map(|package| (package.id.as_str(), package))
+        .collect::<HashMap<_, _>>();
+
+    let mut seen = HashSet::from([package_id]);
+    let mut queue = VecDeque::from([package_id]);
+    let mut dependencies = Vec::new();
+
+    while let Some(id) = queue.pop_front() {
+        let Some(node) = nodes.get(id) else { continue };
+        for dep in &node.deps {
+            let normal = dep.dep_kinds.iter().any(|kind| kind.kind.is_none());
+            if !normal || !seen.insert(&dep.pkg) {
+                continue;
+            }
+            queue.push_back(&dep.pkg);
+
+            let Some(package) = packages.get(dep.pkg.as_str()) else { continue };
+            let Some(lib_target) = package.lib_target() else { continue };
+            let direct = id == package_id;
+            dependencies.push(Dependency {
+                name: package.name.clone(),
+                lib_name: lib_target.name.replace('-', "_"),
+                version: package.version.clone(),
+                source: package.source.clone(),
+                dependency_key: direct.then(|| dep.name.clone()),
+            });
+        }
+    }
+
+    dependencies
+}
+
+/// Match rustdoc's external crates to the packages they come from.
+///
+/// rustdoc only knows the crate name, so when several versions of a crate are in the dependency
+/// graph the version in the crate's docs.rs URL is used to tell them apart. Crates that can't be
+/// matched, such as `std`, are left out.
+pub fn resolve(krate: &Crate, dependencies: &[Dependency]) -> HashMap<u32, Dependency> {
+    krate
+        .external_crates
+        .iter()
+        .filter_map(|(crate_id, external_crate)| {
+            let candidates = dependencies
+                .iter()
+                .filter(|dependency| dependency.lib_name == external_crate.name)
+                .collect::<Vec<_>>();
+
+            let dependency = match candidates.as_slice() {
+                [dependency] => dependency,
+                _ => {
+                    let version = docs_rs_version(
+                        &external_crate.name,
+                        external_crate.html_root_url.as_deref()?,
+                    )?;
+                    candidates
+                        .into_iter()
+                        .find(|dependency| dependency.version.to_string() == version)?
+                }
+            };
+            Some((*crate_id, dependency.clone()))
+        })
+        .collect()
+}
+
+/// The version in a docs.rs URL, e.g. `https://docs.rs/http/0.2.9/`.
+pub fn docs_rs_version<'a>(name: &str, html_root_url: &'a str) -> Option<&'a str> {
+    let mut segments = html_root_url.trim_end_matches('/').rsplit('/');
+    let version = segments.next()?;
+    let url_name = segments.next()?;
+
+    let is_version = version.starts_with(|c: char| c.is_ascii_digit());
+    (url_name.replace('-', "_") == name && is_version).then_some(version)
+}
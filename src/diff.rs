@@ -0,0 +1,277 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{ensure, Context, Result};
+use clap::Args;
+use tempfile::TempDir;
+
+use crate::{
+    analyze::{self, CrateRef, Hidden},
+    build_docs::{self, Features},
+    check,
+    workspace::{self, Member},
+};
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// Git revision to compare against, e.g. `main`.
+    pub base: String,
+
+    /// Git revision to compare. Defaults to the working tree.
+    pub head: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffJsonArgs {
+    /// rustdoc JSON of the old version.
+    pub old: PathBuf,
+
+    /// rustdoc JSON of the new version.
+    pub new: PathBuf,
+}
+
+/// Crates and items in the public API, as returned by [`analyze::AnalyzeOutput::public_items`].
+type PublicItems = BTreeMap<CrateRef, BTreeSet<String>>;
+
+/// Report the crates and items that were added to or removed from `member`'s public API between
+/// two git revisions.
+///
+/// Returns whether nothing was added, like [`run_json`]. Each revision is checked out into a
+/// temporary git worktree and built there. Without a head revision `head_items` are used, which is
+/// the analysis of the working tree.
+pub fn run(
+    member: &Member,
+    args: &DiffArgs,
+    head_items: impl FnOnce() -> Result<PublicItems>,
+    features: &Features,
+    include_std: bool,
+    hidden: Hidden,
+    show_package: bool,
+) -> Result<bool> {
+    let DiffArgs { base, head } = args;
+
+    let base_items = public_items_at(member, base, features, include_std, hidden)?;
+    let head_items = match head {
+        Some(head) => public_items_at(member, head, features, include_std, hidden)?,
+        None => head_items()?,
+    };
+
+    let prefix = if show_package {
+        format!("{}: ", member.name)
+    } else {
+        String::new()
+    };
+
+    report(&base_items, &head_items, &prefix)
+}
+
+/// Report the crates and items that were added to or removed from the public API between two
+/// rustdoc JSON files.
+///
+/// Returns whether nothing was added, so CI can fail on new exposures. The files are analyzed
+/// without a dependency graph, so crate versions come from their docs.rs URLs.
+pub fn run_json(args: &DiffJsonArgs, include_std: bool, hidden: Hidden) -> Result<bool> {
+    let DiffJsonArgs { old, new } = args;
+
+    let public_items = |path: &PathBuf| {
+        // the files may come from anywhere, so source files are looked for from the current
+        // directory
+        analyze::run(path, Path::new(""), &[], include_std, hidden)
+            .and_then(|analyze_output| analyze_output.public_items())
+            .with_context(|| format!("failed to analyze {}", path.display()))
+    };
+    let old_items = public_items(old)?;
+    let new_items = public_items(new)?;
+
+    report(&old_items, &new_items, "")
+}
+
+/// Print the items added and removed between `base` and `head`, grouped by crate, and the crates
+/// whose version changed.
+///
+/// Returns whether nothing was added. A new version of a crate that was already exposed isn't a
+/// failure by itself, only the items it adds are.
+fn report(base: &PublicItems, head: &PublicItems, prefix: &str) -> Result<bool> {
+    let added = changes(base, head, "new in public API");
+    let removed = changes(head, base, "no longer in public API");
+    let updated = updates(base, head);
+
+    let mut stdout = std::io::stdout().lock();
+
+    if added.is_empty() && removed.is_empty() && updated.is_empty() {
+        writeln!(
+            &mut stdout,
+            "{prefix}No changes to the crates in public API"
+        )?;
+        return Ok(true);
+    }
+
+    let success = added.is_empty();
+    let sections = [
+        ("Added to public API:", added),
+        ("Removed from public API:", removed),
+        ("Updated in public API:", updated),
+    ];
+    for (title, changes) in sections {
+        if changes.is_empty() {
+            continue;
+        }
+        writeln!(&mut stdout, "{prefix}{title}")?;
+        for (krate, paths) in changes {
+            writeln!(&mut stdout, "    {krate}")?;
+            for path in paths {
+                writeln!(&mut stdout, "        {path}")?;
+            }
+        }
+    }
+
+    Ok(success)
+}
+
+/// The items in `to` that aren't in `from`, by crate. Crates that aren't in `from` at all are
+/// described with `whole_crate`.
+fn changes(from: &PublicItems, to: &PublicItems, whole_crate: &str) -> Vec<(String, Vec<String>)> {
+    let from = by_crate_key(from);
+    to.iter()
+        .filter_map(|(krate, paths)| {
+            let (header, paths) = match from.get(&crate_key(krate)) {
+                Some((_, from_paths)) => (
+                    krate.to_string(),
+                    paths.difference(from_paths).cloned().collect::<Vec<_>>(),
+                ),
+                None => (
+                    format!("{krate} ({whole_crate})"),
+                    paths.iter().cloned().collect(),
+                ),
+            };
+            (!paths.is_empty()).then_some((header, paths))
+        })
+        .collect()
+}
+
+/// The crates in both `base` and `head` whose version changed, e.g. `http 0.2.9 -> 0.2.11`.
+fn updates(base: &PublicItems, head: &PublicItems) -> Vec<(String, Vec<String>)> {
+    let base = by_crate_key(base);
+    head.keys()
+        .filter_map(|krate| {
+            let (base_krate, _) = base.get(&crate_key(krate))?;
+            let (Some(from), Some(to)) = (&base_krate.version, &krate.version) else { return None };
+            (from != to).then(|| (format!("{} {from} -> {to}", krate.name), Vec::new()))
+        })
+        .collect()
+}
+
+/// A crate's name and the part of its version that semver-compatible versions share, e.g.
+/// `("http", "0.2")` for http 0.2.9, so a crate is still matched up after `Cargo.lock` updates it.
+type CrateKey = (String, Option<String>);
+
+fn crate_key(krate: &CrateRef) -> CrateKey {
+    (
+        krate.name.clone(),
+        krate.version.as_ref().map(check::compatible_req),
+    )
+}
+
+fn by_crate_key(items: &PublicItems) -> BTreeMap<CrateKey, (&CrateRef, &BTreeSet<String>)> {
+    items
+        .iter()
+        .map(|(krate, paths)| (crate_key(krate), (krate, paths)))
+        .collect()
+}
+
+/// Build and analyze `member` as of `rev`.
+fn public_items_at(
+    member: &Member,
+    rev: &str,
+    features: &Features,
+    include_std: bool,
+    hidden: Hidden,
+) -> Result<PublicItems> {
+    let manifest_dir = member
+        .manifest_path
+        .parent()
+        .context("manifest path has no parent")?;
+    let repo = git(manifest_dir, &["rev-parse", "--show-toplevel"])?;
+    let repo = PathBuf::from(repo.trim()).canonicalize()?;
+    let manifest_path = member.manifest_path.canonicalize()?;
+    let manifest_path = manifest_path
+        .strip_prefix(&repo)
+        .with_context(|| format!("{} isn't in {}", manifest_path.display(), repo.display()))?;
+
+    let worktree = Worktree::add(&repo, rev)?;
+
+    let analyze = || {
+        let manifest_path = worktree.dir.path().join(manifest_path);
+        let worktree_member = workspace::members(Some(&manifest_path), false, &[])?
+            .into_iter()
+            .next()
+            .context("package not found")?;
+        // the toolchain may have been given on the command line
+        let member = Member {
+            toolchain: member.toolchain.clone(),
+            ..worktree_member
+        };
+        let doc_json_path = build_docs::run(&member, features, hidden, false)?;
+        analyze::run(
+            &doc_json_path,
+            member.manifest_dir(),
+            &member.dependencies,
+            include_std,
+            hidden,
+        )?
+        .public_items()
+    };
+    analyze().with_context(|| format!("failed to analyze `{}` at `{rev}`", member.name))
+}
+
+/// A git worktree in a temporary directory, removed on drop.
+struct Worktree {
+    repo: PathBuf,
+    dir: TempDir,
+}
+
+impl Worktree {
+    fn add(repo: &Path, rev: &str) -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("cargo-public-api-crates-")
+            .tempdir()
+            .context("failed to create temporary directory")?;
+        let dir_arg = dir
+            .path()
+            .to_str()
+            .context("temporary directory isn't UTF-8")?;
+        git(
+            repo,
+            &["worktree", "add", "--detach", "--quiet", dir_arg, rev],
+        )
+        .with_context(|| format!("failed to check out `{rev}`"))?;
+        Ok(Self {
+            repo: repo.to_owned(),
+            dir,
+        })
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        let dir = self.dir.path().to_string_lossy();
+        // also deletes the directory, which `TempDir` would otherwise do
+        let _ = git(&self.repo, &["worktree", "remove", "--force", &dir]);
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed to run `git`")?;
+    ensure!(output.status.success(), "`git {}` failed", args.join(" "));
+    String::from_utf8(output.stdout).context("`git` output isn't UTF-8")
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    analyze::{self, Hidden},
+    build_docs::{self, Features},
+    workspace::Member,
+};
+
+/// A set of the feature configurations that are built.
+#[derive(Serialize, Debug, Default)]
+pub struct Configurations {
+    pub default_features: bool,
+    /// Features enabled on their own, without default features.
+    pub features: BTreeSet<String>,
+    pub all_features: bool,
+}
+
+impl Configurations {
+    pub fn is_empty(&self) -> bool {
+        !self.default_features && self.features.is_empty() && !self.all_features
+    }
+
+    /// E.g. `default features`, `feature `serde``, and `all features`.
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        if self.default_features {
+            labels.push(Configuration::Default.to_string());
+        }
+        labels.extend(
+            self.features
+                .iter()
+                .map(|feature| Configuration::Feature(feature.clone()).to_string()),
+        );
+        if self.all_features {
+            labels.push(Configuration::All.to_string());
+        }
+        labels
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FeatureMatrix {
+    /// Map from crate name to the configurations that expose it.
+    pub crates: BTreeMap<String, Configurations>,
+    /// Configurations whose docs failed to build, e.g. all features when some features are
+    /// mutually exclusive.
+    pub failed: Configurations,
+}
+
+enum Configuration {
+    Default,
+    Feature(String),
+    All,
+}
+
+impl Configuration {
+    fn features(&self) -> Features {
+        match self {
+            Self::Default => Features::default(),
+            Self::Feature(feature) => Features {
+                features: vec![feature.clone()],
+                no_default_features: true,
+                ..Default::default()
+            },
+            Self::All => Features {
+                all_features: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn add_to(&self, configurations: &mut Configurations) {
+        match self {
+            Self::Default => configurations.default_features = true,
+            Self::Feature(feature) => {
+                configurations.features.insert(feature.clone());
+            }
+            Self::All => configurations.all_features = true,
+        }
+    }
+}
+
+impl fmt::Display for Configuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default features"),
+            Self::Feature(feature) => write!(f, "feature `{feature}`"),
+            Self::All => write!(f, "all features"),
+        }
+    }
+}
+
+/// Build and analyze `member` with default features, each feature on its own, and all features.
+///
+/// A configuration that fails to build is recorded and the rest are still built, since features
+/// that are mutually exclusive can't be built together.
+pub fn run(member: &Member, include_std: bool, hidden: Hidden) -> Result<FeatureMatrix> {
+    let mut matrix = FeatureMatrix::default();
+
+    let configurations = std::iter::once(Configuration::Default)
+        .chain(member.features.iter().cloned().map(Configuration::Feature))
+        .chain(std::iter::once(Configuration::All));
+
+    for configuration in configurations {
+        let features = configuration.features();
+        match crates_in_public_api(member, &features, include_std, hidden) {
+            Ok(names) => {
+                for name in names {
+                    configuration.add_to(matrix.crates.entry(name).or_default());
+                }
+            }
+            Err(err) => {
+                eprintln!(
+                    "warning: failed to build docs for `{}` with {configuration}: {err:#}",
+                    member.name
+                );
+                configuration.add_to(&mut matrix.failed);
+            }
+        }
+    }
+
+    Ok(matrix)
+}
+
+fn crates_in_public_api(
+    member: &Member,
+    features: &Features,
+    include_std: bool,
+    hidden: Hidden,
+) -> Result<BTreeSet<String>> {
+    let doc_json_path = build_docs::run(member, features, hidden, false)?;
+    analyze::run(
+        &doc_json_path,
+        member.manifest_dir(),
+        &member.dependencies,
+        include_std,
+        hidden,
+    )?
+    .crates_in_public_api()
+}
@@ -0,0 +1,320 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use rustdoc_types::{Crate, Id, ItemEnum, ItemKind, Span};
+
+/// Finds the external items that exported `macro_rules!` macros refer to.
+///
+/// rustdoc replaces the body of each rule with `{ ... }`, so the macro's definition is read from
+/// its source file and the paths in it are found by scanning the tokens. Paths that start with the
+/// name of an external crate, like `::http::Method`, are looked up in that crate. Paths that start
+/// with `$crate` are followed through the crate's re-exports, so
+/// `$crate::__private::serde::Serialize` is found if `__private` has a `pub use serde;`.
+pub struct MacroScanner<'a> {
+    krate: &'a Crate,
+    /// Public paths of the re-exports of external items, and the item they re-export.
+    reexports: HashMap<Vec<String>, &'a Id>,
+    /// Items in external crates by crate and path.
+    external_paths: HashMap<(u32, String), &'a Id>,
+    /// Where the source files named in spans are looked for.
+    source_dir: &'a Path,
+}
+
+impl<'a> MacroScanner<'a> {
+    pub fn new(
+        krate: &'a Crate,
+        public_paths: &HashMap<Id, Vec<String>>,
+        source_dir: &'a Path,
+    ) -> Self {
+        let external_paths = krate
+            .paths
+            .iter()
+            .filter(|(_, item)| krate.external_crates.contains_key(&item.crate_id))
+            .map(|(id, item)| ((item.crate_id, item.path.join("::")), id))
+            .collect::<HashMap<_, _>>();
+
+        let mut reexports = HashMap::new();
+        for (id, path) in public_paths {
+            let target = match &krate.index[id].inner {
+                ItemEnum::Import(import) if !import.glob => import
+                    .id
+                    .as_ref()
+                    .and_then(|id| krate.paths.get_key_value(id))
+                    .filter(|(_, item)| krate.external_crates.contains_key(&item.crate_id))
+                    .map(|(id, _)| id),
+                ItemEnum::ExternCrate { name, rename: _ } => {
+                    krate.paths.iter().find_map(|(id, item)| {
+                        let is_root = matches!(item.kind, ItemKind::Module)
+                            && item.path.len() == 1
+                            && &item.path[0] == name
+                            && krate.external_crates.contains_key(&item.crate_id);
+                        is_root.then_some(id)
+                    })
+                }
+                _ => None,
+            };
+            if let Some(target) = target {
+                reexports.insert(path.clone(), target);
+            }
+        }
+
+        Self {
+            krate,
+            reexports,
+            external_paths,
+            source_dir,
+        }
+    }
+
+    /// External items referred to by a `macro_rules!` macro defined at `span`.
+    ///
+    /// Falls back to `source`, the macro as rustdoc gives it, if the source file can't be read.
+    pub fn external_items(&self, source: &str, span: Option<&Span>) -> HashSet<&'a Id> {
+        let definition = span.and_then(|span| read_span(self.source_dir, span));
+        let source = definition.as_deref().unwrap_or(source);
+
+        let mut items = HashSet::new();
+        for path in paths(source) {
+            if path[0] == "$crate" {
+                items.extend(self.resolve_local(&path[1..]));
+            } else {
+                for (crate_id, krate) in &self.krate.external_crates {
+                    if krate.name == path[0] {
+                        items.extend(self.resolve_external(*crate_id, &path));
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    /// Resolve a path relative to the crate root by finding the re-export it goes through.
+    fn resolve_local(&self, segments: &[&str]) -> Option<&'a Id> {
+        let root = self.krate.index.get(&self.krate.root)?.name.clone()?;
+        let path = std::iter::once(root)
+            .chain(segments.iter().map(|segment| segment.to_string()))
+            .collect::<Vec<_>>();
+
+        (2..=path.len()).find_map(|len| {
+            let target = self.reexports.get(&path[..len])?;
+            let item = self.krate.paths.get(target)?;
+            let rest = path[len..].iter().map(String::as_str);
+            let external_path = item
+                .path
+                .iter()
+                .map(String::as_str)
+                .chain(rest)
+                .collect::<Vec<_>>();
+            self.resolve_external(item.crate_id, &external_path)
+        })
+    }
+
+    /// Resolve a path in an external crate to the item it names, or the closest item we know of.
+    fn resolve_external(&self, crate_id: u32, segments: &[&str]) -> Option<&'a Id> {
+        let longest_prefix = (1..=segments.len()).rev().find_map(|len| {
+            let id = self
+                .external_paths
+                .get(&(crate_id, segments[..len].join("::")))?;
+            Some((len, *id))
+        });
+        if let Some((len, id)) = longest_prefix {
+            if len == segments.len() {
+                return Some(id);
+            }
+        }
+
+        // items are often re-exported closer to the crate root than where they're defined, e.g.
+        // `http::Method` is defined at `http::method::Method`
+        for name in segments[1..].iter().rev() {
+            let mut candidates = self.external_paths.iter().filter(|((id, path), _)| {
+                *id == crate_id && path.rsplit("::").next() == Some(name)
+            });
+            if let (Some((_, id)), None) = (candidates.next(), candidates.next()) {
+                return Some(id);
+            }
+        }
+
+        longest_prefix.map(|(_, id)| id)
+    }
+}
+
+/// The lines of the source file that `span` covers.
+///
+/// Cargo runs rustdoc in the workspace root, so a relative file name is looked for in `source_dir`
+/// and then in its parent directories.
+fn read_span(source_dir: &Path, span: &Span) -> Option<String> {
+    let path = source_dir
+        .ancestors()
+        .map(|dir| dir.join(&span.filename))
+        .find(|path| path.is_file())?;
+    let source = std::fs::read_to_string(path).ok()?;
+
+    let (begin, end) = (span.begin.0, span.end.0);
+    let len = (end + 1).checked_sub(begin)?;
+    let lines = source
+        .lines()
+        .skip(begin.checked_sub(1)?)
+        .take(len)
+        .collect::<Vec<_>>();
+    (lines.len() == len).then(|| lines.join("\n"))
+}
+
+/// The paths with at least two segments in a macro's source, e.g. `$crate::Foo` or
+/// `::http::Method`. A leading `::` is dropped.
+fn paths(source: &str) -> Vec<Vec<&str>> {
+    let mut paths = Vec::new();
+    let mut path = Vec::new();
+    let mut after_colons = false;
+
+    fn flush<'a>(paths: &mut Vec<Vec<&'a str>>, path: &mut Vec<&'a str>) {
+        if path.len() >= 2 {
+            paths.push(std::mem::take(path));
+        } else {
+            path.clear();
+        }
+    }
+
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c == ':' && chars.peek().is_some_and(|(_, c)| *c == ':') {
+            chars.next();
+            after_colons = true;
+        } else if c == '$' || c == '_' || c.is_alphabetic() {
+            let mut end = start + c.len_utf8();
+            while let Some((idx, c)) = chars.peek().copied() {
+                if c != '_' && !c.is_alphanumeric() {
+                    break;
+                }
+                end = idx + c.len_utf8();
+                chars.next();
+            }
+            if !after_colons {
+                flush(&mut paths, &mut path);
+            }
+            path.push(&source[start..end]);
+            after_colons = false;
+        } else if c == '"' {
+            // skip string literals
+            flush(&mut paths, &mut path);
+            after_colons = false;
+            while let Some((_, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => break,
+                    _ => {}
+                }
+            }
+        } else if !c.is_whitespace() {
+            flush(&mut paths, &mut path);
+            after_colons = false;
+        }
+    }
+    flush(&mut paths, &mut path);
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIB_RS: &str = r#"//! A client.
+
+#[macro_export]
+macro_rules! request {
+    ($uri:expr) => {
+        ::http::Request::builder()
+            .method(::http::Method::GET)
+            .uri($uri)
+    };
+    () => {
+        $crate::__private::Response::new("::not::a::path")
+    };
+}
+"#;
+
+    /// The macro as rustdoc gives it.
+    const RUSTDOC_SOURCE: &str =
+        "macro_rules! request {\n    ($uri:expr) => { ... };\n    () => { ... };\n}";
+
+    /// A crate with the macro, and `pub use http::response::Response;` in `__private`.
+    fn krate() -> Crate {
+        let item = |id: &str, name: &str, inner: serde_json::Value| {
+            serde_json::json!({
+                "id": id, "crate_id": 0, "name": name, "span": null, "visibility": "public",
+                "docs": null, "links": {}, "attrs": [], "deprecation": null, "inner": inner,
+            })
+        };
+        let summary = |crate_id: u32, path: &[&str], kind: &str| {
+            serde_json::json!({
+                "crate_id": crate_id, "path": path, "kind": kind,
+            })
+        };
+        serde_json::from_value(serde_json::json!({
+            "root": "0:0",
+            "crate_version": null,
+            "includes_private": false,
+            "index": {
+                "0:0": item("0:0", "mycrate", serde_json::json!({
+                    "module": { "is_crate": true, "items": ["0:1", "0:2"], "is_stripped": false },
+                })),
+                "0:1": item("0:1", "request", serde_json::json!({ "macro": RUSTDOC_SOURCE })),
+                "0:2": item("0:2", "__private", serde_json::json!({
+                    "module": { "is_crate": false, "items": ["0:3"], "is_stripped": false },
+                })),
+                "0:3": item("0:3", "Response", serde_json::json!({
+                    "import": {
+                        "source": "http::response::Response",
+                        "name": "Response",
+                        "id": "1:3",
+                        "glob": false,
+                    },
+                })),
+            },
+            "paths": {
+                "0:0": summary(0, &["mycrate"], "module"),
+                "1:1": summary(1, &["http", "request", "Request"], "struct"),
+                "1:2": summary(1, &["http", "method", "Method"], "struct"),
+                "1:3": summary(1, &["http", "response", "Response"], "struct"),
+            },
+            "external_crates": { "1": { "name": "http", "html_root_url": null } },
+            "format_version": rustdoc_types::FORMAT_VERSION,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn macro_source_from_span() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), LIB_RS).unwrap();
+
+        let krate = krate();
+        let public_paths = HashMap::from([(
+            Id("0:3".to_owned()),
+            vec![
+                "mycrate".to_owned(),
+                "__private".to_owned(),
+                "Response".to_owned(),
+            ],
+        )]);
+        let scanner = MacroScanner::new(&krate, &public_paths, dir.path());
+        let span = Span {
+            filename: "src/lib.rs".into(),
+            begin: (4, 0),
+            end: (13, 1),
+        };
+
+        let expected = ["1:1", "1:2", "1:3"].map(|id| Id(id.to_owned()));
+        assert_eq!(
+            scanner.external_items(RUSTDOC_SOURCE, Some(&span)),
+            expected.iter().collect()
+        );
+        // without the source file only the matchers are left, which don't name any items
+        assert!(scanner.external_items(RUSTDOC_SOURCE, None).is_empty());
+    }
+}
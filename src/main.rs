@@ -1,14 +1,31 @@
 use std::{env, path::PathBuf};
 
-use anyhow::{Context, Result};
+use analyze::Hidden;
+use anyhow::{bail, Context, Result};
+use build_docs::{Features, Toolchain};
+use check::CheckArgs;
 use clap::{Parser, Subcommand};
+use diff::{DiffArgs, DiffJsonArgs};
+use output::Format;
 use serde::de::DeserializeOwned;
+use workspace::Member;
 
 mod analyze;
+mod baseline;
 mod build_docs;
 mod check;
+mod dependencies;
+mod diff;
+mod feature_matrix;
+mod macros;
+mod manifest;
+mod metadata;
 mod output;
+mod published;
+mod reachability;
+mod transitive;
 mod visit;
+mod workspace;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -16,41 +33,208 @@ struct Args {
     #[arg(long)]
     include_std: bool,
 
+    /// How to treat public items marked `#[doc(hidden)]`.
+    #[arg(long, value_enum, default_value_t)]
+    hidden: Hidden,
+
     /// Path to Cargo.toml
     #[arg(long)]
     manifest_path: Option<PathBuf>,
 
+    /// Analyze a published crate instead of the current package: a `.crate` file, an unpacked
+    /// crate directory, or `name@version` from the local registry cache. The crate is copied to a
+    /// temporary directory and built there.
+    #[arg(
+        long = "crate",
+        value_name = "PATH|NAME@VERSION",
+        conflicts_with_all = ["manifest_path", "workspace", "packages", "skip_build"],
+    )]
+    krate: Option<String>,
+
     /// Skip building the documentation.
     #[arg(long)]
     skip_build: bool,
 
+    /// Analyze every library package in the workspace.
+    #[arg(long)]
+    workspace: bool,
+
+    /// Package to analyze. Can be given multiple times.
+    #[arg(short, long = "package", value_name = "SPEC")]
+    packages: Vec<String>,
+
+    #[command(flatten)]
+    features: Features,
+
+    /// Toolchain to build the docs with, e.g. `nightly-2023-05-01`, or `none` to run `cargo`
+    /// without a toolchain override so `rust-toolchain.toml` applies. Defaults to
+    /// `$CARGO_PUBLIC_API_CRATES_TOOLCHAIN`, then `toolchain` in
+    /// `[package.metadata.cargo-public-api-crates]` or the workspace equivalent, then `nightly`.
+    #[arg(long)]
+    toolchain: Option<Toolchain>,
+
+    /// Report which features bring each crate into the public API. Builds the docs with default
+    /// features, each feature on its own, and all features.
+    #[arg(
+        long,
+        conflicts_with_all = ["features", "all_features", "no_default_features", "skip_build"],
+    )]
+    feature_matrix: bool,
+
+    /// Also report the crates exposed by the items this crate exposes from its dependencies, and
+    /// so on, which users are coupled to as well. Builds the docs of each exposed dependency.
+    #[arg(long, conflicts_with_all = ["feature_matrix", "skip_build"])]
+    transitive: bool,
+
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+
     #[command(subcommand)]
     cmd: Option<Command>,
 }
 
 #[derive(Debug, Subcommand)]
 enum Command {
-    Check,
+    /// Check the crates in the public API against the allow-list in Cargo.toml.
+    Check(CheckArgs),
+    /// Add `[package.metadata.cargo-public-api-crates]` to Cargo.toml, allowing the crates
+    /// currently in the public API.
+    Init,
+    /// Compare the crates in the public API between two git revisions. Exits with an error if
+    /// crates or items were added.
+    Diff(DiffArgs),
+    /// Compare the crates in the public API between two rustdoc JSON files. Exits with an error
+    /// if crates or items were added.
+    DiffJson(DiffJsonArgs),
 }
 
 fn main() -> Result<()> {
     let raw_args = env::args().skip(if running_as_cargo_cmd() { 1 } else { 0 });
     let Args {
         include_std,
+        hidden,
         manifest_path,
+        krate,
         skip_build,
+        workspace,
+        packages,
+        features,
+        toolchain,
+        feature_matrix,
+        transitive,
+        format,
         cmd,
     } = Args::parse_from(raw_args);
 
-    let doc_json_path = build_docs::run(manifest_path.clone(), skip_build)?;
-    let analyze_output = analyze::run(&doc_json_path, include_std)?;
+    if cmd.is_some() && feature_matrix {
+        bail!("`--feature-matrix` can only be used without a subcommand");
+    }
+    if cmd.is_some() && transitive {
+        bail!("`--transitive` can only be used without a subcommand");
+    }
+
+    // works on files that are already built, so there's no package to look up
+    if let Some(Command::DiffJson(args)) = &cmd {
+        if !diff::run_json(args, include_std, hidden)? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if cmd.is_some() && krate.is_some() {
+        bail!("`--crate` can only be used without a subcommand");
+    }
+    // kept until the end so the temporary directory isn't removed before the build
+    let scratch = krate.as_deref().map(published::unpack).transpose()?;
+    let manifest_path = match &scratch {
+        Some(scratch) => Some(scratch.manifest_path.clone()),
+        None => manifest_path,
+    };
+
+    let mut members = workspace::members(manifest_path.as_deref(), workspace, &packages)?;
+    if let Some(toolchain) = toolchain {
+        for member in &mut members {
+            member.toolchain = toolchain.clone();
+        }
+    }
+
+    let analyze_member = |member: &Member| {
+        let doc_json_path = build_docs::run(member, &features, hidden, skip_build)?;
+        analyze::run(
+            &doc_json_path,
+            member.manifest_dir(),
+            &member.dependencies,
+            include_std,
+            hidden,
+        )
+    };
 
     match cmd {
-        Some(Command::Check) => {
-            check::run(manifest_path, analyze_output)?;
+        Some(Command::Check(args)) => {
+            let mut success = true;
+            for member in &members {
+                let analyze_output = analyze_member(member)?;
+                success &= check::run(member, analyze_output, &args, members.len() > 1)?;
+            }
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Init) => {
+            for member in &members {
+                check::init(member, analyze_member(member)?)?;
+            }
+        }
+        Some(Command::Diff(args)) => {
+            let mut success = true;
+            for member in &members {
+                success &= diff::run(
+                    member,
+                    &args,
+                    || analyze_member(member)?.public_items(),
+                    &features,
+                    include_std,
+                    hidden,
+                    members.len() > 1,
+                )?;
+            }
+            if !success {
+                std::process::exit(1);
+            }
+        }
+        Some(Command::DiffJson(_)) => unreachable!("handled before looking up packages"),
+        None if feature_matrix => {
+            let reports = members
+                .iter()
+                .map(|member| {
+                    Ok((
+                        member.name.clone(),
+                        feature_matrix::run(member, include_std, hidden)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            output::feature_matrix::run(reports, format)?;
+        }
+        None if transitive => {
+            let reports = members
+                .iter()
+                .map(|member| {
+                    let analyze_output = analyze_member(member)?;
+                    Ok((
+                        member.name.clone(),
+                        transitive::run(member, analyze_output, &features, include_std, hidden)?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            output::transitive::run(reports, format)?;
         }
         None => {
-            output::run(analyze_output)?;
+            let reports = members
+                .iter()
+                .map(|member| Ok((member.name.clone(), analyze_member(member)?)))
+                .collect::<Result<Vec<_>>>()?;
+            output::run(reports, format)?;
         }
     }
 
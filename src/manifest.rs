@@ -0,0 +1,402 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use itertools::Itertools;
+use toml_edit::{Array, Document, InlineTable, Item, Table, TableLike, Value};
+
+/// Whether the manifest has a `[package.metadata.cargo-public-api-crates]` table.
+pub fn has_table(manifest_path: &Path) -> Result<bool> {
+    let doc = read(manifest_path)?;
+    Ok(doc
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("cargo-public-api-crates"))
+        .is_some())
+}
+
+/// Update a list in `[package.metadata.cargo-public-api-crates]`, such as `allowed`.
+///
+/// Entries in `remove` are removed and entries in `add` are inserted in sorted order. The rest of
+/// the manifest, including comments and formatting, is left as is. The table and list are created
+/// if they don't exist.
+pub fn update_list(
+    manifest_path: &Path,
+    key: &str,
+    add: &BTreeSet<String>,
+    remove: &BTreeSet<String>,
+) -> Result<()> {
+    if add.is_empty() && remove.is_empty() {
+        return Ok(());
+    }
+
+    let mut doc = read(manifest_path)?;
+
+    let list = list_array(&mut doc, key).with_context(|| {
+        format!(
+            "failed to update `package.metadata.cargo-public-api-crates.{key}` in {}",
+            manifest_path.display()
+        )
+    })?;
+
+    update_array(list, add, remove);
+
+    std::fs::write(manifest_path, doc.to_string())
+        .with_context(|| format!("failed to write {}", manifest_path.display()))
+}
+
+/// Create `[package.metadata.cargo-public-api-crates]` with a list of `entries` at `key`, even if
+/// `entries` is empty.
+pub fn create_list(manifest_path: &Path, key: &str, entries: &BTreeSet<String>) -> Result<()> {
+    let mut doc = read(manifest_path)?;
+
+    let list = list_array(&mut doc, key).with_context(|| {
+        format!(
+            "failed to create `package.metadata.cargo-public-api-crates.{key}` in {}",
+            manifest_path.display()
+        )
+    })?;
+
+    update_array(list, entries, &BTreeSet::new());
+
+    std::fs::write(manifest_path, doc.to_string())
+        .with_context(|| format!("failed to write {}", manifest_path.display()))
+}
+
+/// Update a table in `[package.metadata.cargo-public-api-crates]`, such as `allowed` when it's
+/// written as `allowed = { http = "1" }`.
+///
+/// Keys in `remove` are removed before the entries in `add` are inserted. If the keys were sorted
+/// they're kept sorted, otherwise new entries go at the end.
+pub fn update_table(
+    manifest_path: &Path,
+    key: &str,
+    add: &BTreeMap<String, String>,
+    remove: &BTreeSet<String>,
+) -> Result<()> {
+    if add.is_empty() && remove.is_empty() {
+        return Ok(());
+    }
+
+    let mut doc = read(manifest_path)?;
+
+    let table = metadata_table(&mut doc)
+        .and_then(|table| {
+            table
+                .get_mut(key)
+                .and_then(Item::as_table_like_mut)
+                .with_context(|| {
+                    format!("`package.metadata.cargo-public-api-crates.{key}` isn't a table")
+                })
+        })
+        .with_context(|| {
+            format!(
+                "failed to update `package.metadata.cargo-public-api-crates.{key}` in {}",
+                manifest_path.display()
+            )
+        })?;
+
+    let sorted = table
+        .iter()
+        .map(|(key, _)| key)
+        .tuple_windows()
+        .all(|(a, b)| a <= b);
+
+    for key in remove {
+        table.remove(key);
+    }
+    for (key, value) in add {
+        table.insert(key, toml_edit::value(value.as_str()));
+    }
+
+    if sorted {
+        table.sort_values();
+    }
+
+    std::fs::write(manifest_path, doc.to_string())
+        .with_context(|| format!("failed to write {}", manifest_path.display()))
+}
+
+/// The dependencies in `[dependencies]` and `[target.*.dependencies]`, and whether they're marked
+/// `public = true`.
+pub fn dependencies(manifest_path: &Path) -> Result<BTreeMap<String, bool>> {
+    let mut doc = read(manifest_path)?;
+    let mut dependencies = BTreeMap::<String, bool>::new();
+    for_each_dependency(&mut doc, |name, item| {
+        let public = item
+            .get("public")
+            .and_then(Item::as_bool)
+            .unwrap_or_default();
+        *dependencies.entry(name.to_owned()).or_default() |= public;
+    });
+    Ok(dependencies)
+}
+
+/// Add `public = true` to the dependencies in `add` and remove it from the dependencies in
+/// `remove`.
+///
+/// Dependencies written as just a version, like `http = "1"`, are turned into inline tables.
+pub fn set_public(
+    manifest_path: &Path,
+    add: &BTreeSet<String>,
+    remove: &BTreeSet<String>,
+) -> Result<()> {
+    if add.is_empty() && remove.is_empty() {
+        return Ok(());
+    }
+
+    let mut doc = read(manifest_path)?;
+
+    for_each_dependency(&mut doc, |name, item| {
+        if add.contains(name) {
+            if let Some(Value::String(version)) = item.as_value() {
+                let decor = version.decor().clone();
+                let mut table = InlineTable::new();
+                table.insert("version", version.value().as_str().into());
+                let mut value = Value::InlineTable(table);
+                *value.decor_mut() = decor;
+                *item = Item::Value(value);
+            }
+            if let Some(table) = item.as_table_like_mut() {
+                table.insert("public", toml_edit::value(true));
+            }
+        } else if remove.contains(name) {
+            if let Some(table) = item.as_table_like_mut() {
+                table.remove("public");
+            }
+        } else {
+            return;
+        }
+
+        // keep `{ version = "1", public = true }` on one line with even spacing
+        if let Some(table) = item.as_inline_table_mut() {
+            table.fmt();
+        }
+    });
+
+    std::fs::write(manifest_path, doc.to_string())
+        .with_context(|| format!("failed to write {}", manifest_path.display()))
+}
+
+fn for_each_dependency(doc: &mut Document, mut f: impl FnMut(&str, &mut Item)) {
+    let mut visit_table = |item: Option<&mut Item>| {
+        if let Some(dependencies) = item.and_then(Item::as_table_like_mut) {
+            for (name, item) in dependencies.iter_mut() {
+                f(name.get(), item);
+            }
+        }
+    };
+
+    visit_table(doc.get_mut("dependencies"));
+
+    if let Some(targets) = doc.get_mut("target").and_then(Item::as_table_like_mut) {
+        for (_, target) in targets.iter_mut() {
+            visit_table(target.get_mut("dependencies"));
+        }
+    }
+}
+
+/// Add an empty `[workspace]` table so the package is built on its own, even if it's inside
+/// another workspace.
+pub fn make_workspace_root(manifest_path: &Path) -> Result<()> {
+    let mut doc = read(manifest_path)?;
+    if doc.contains_key("workspace") {
+        return Ok(());
+    }
+    doc.insert("workspace", Item::Table(Table::new()));
+
+    std::fs::write(manifest_path, doc.to_string())
+        .with_context(|| format!("failed to write {}", manifest_path.display()))
+}
+
+fn read(manifest_path: &Path) -> Result<Document> {
+    let toml = std::fs::read_to_string(manifest_path)
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+    toml.parse::<Document>()
+        .with_context(|| format!("failed to parse {}", manifest_path.display()))
+}
+
+fn list_array<'a>(doc: &'a mut Document, key: &str) -> Result<&'a mut Array> {
+    metadata_table(doc)?
+        .entry(key)
+        .or_insert_with(|| toml_edit::value(Array::new()))
+        .as_array_mut()
+        .with_context(|| format!("`package.metadata.cargo-public-api-crates.{key}` isn't an array"))
+}
+
+/// `[package.metadata.cargo-public-api-crates]`, created if it doesn't exist.
+fn metadata_table(doc: &mut Document) -> Result<&mut dyn TableLike> {
+    let package = doc
+        .get_mut("package")
+        .and_then(Item::as_table_like_mut)
+        .context("`[package]` not found")?;
+
+    let metadata = package.entry("metadata").or_insert_with(|| {
+        let mut table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    });
+    let metadata = metadata
+        .as_table_like_mut()
+        .context("`package.metadata` isn't a table")?;
+
+    metadata
+        .entry("cargo-public-api-crates")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_like_mut()
+        .context("`package.metadata.cargo-public-api-crates` isn't a table")
+}
+
+fn update_array(array: &mut Array, add: &BTreeSet<String>, remove: &BTreeSet<String>) {
+    let multiline = array.trailing().as_str().unwrap_or_default().contains('\n')
+        || array.iter().any(|value| prefix(value).contains('\n'));
+
+    let mut idx = 0;
+    while idx < array.len() {
+        let remove_value = array
+            .get(idx)
+            .and_then(Value::as_str)
+            .is_some_and(|value| remove.contains(value));
+        if remove_value {
+            let removed = array.remove(idx);
+
+            // a comment after the previous entry is stored in the prefix of the removed one
+            if let Some((comment, _)) = prefix(&removed).split_once('\n') {
+                if !comment.trim().is_empty() {
+                    if let Some(next) = array.get_mut(idx) {
+                        let next_prefix = format!("{comment}\n{}", prefix(next));
+                        next.decor_mut().set_prefix(next_prefix);
+                    } else {
+                        let trailing = array.trailing().as_str().unwrap_or_default();
+                        let trailing = format!(
+                            "{comment}\n{}",
+                            trailing.strip_prefix('\n').unwrap_or(trailing)
+                        );
+                        array.set_trailing(trailing);
+                    }
+                }
+            }
+        } else {
+            idx += 1;
+        }
+    }
+
+    // match the indentation of the existing entries, without copying their comments
+    let indent = array
+        .iter()
+        .map(prefix)
+        .find_map(|prefix| prefix.rsplit_once('\n'))
+        .map_or("    ", |(_, indent)| indent);
+    let new_prefix = format!("\n{indent}");
+
+    for entry in add {
+        let idx = array
+            .iter()
+            .position(|value| value.as_str().is_some_and(|value| value > entry.as_str()))
+            .unwrap_or(array.len());
+
+        if multiline {
+            array.insert_formatted(idx, Value::from(entry.as_str()).decorated(&new_prefix, ""));
+        } else {
+            array.insert(idx, entry.as_str());
+        }
+    }
+
+    if !multiline {
+        array.fmt();
+    }
+}
+
+fn prefix(value: &Value) -> &str {
+    value
+        .decor()
+        .prefix()
+        .and_then(|prefix| prefix.as_str())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(toml: &str, add: &[&str], remove: &[&str]) -> String {
+        let mut doc = toml.parse::<Document>().unwrap();
+        let array = doc["allowed"].as_array_mut().unwrap();
+        let set = |entries: &[&str]| entries.iter().map(|entry| entry.to_string()).collect();
+        update_array(array, &set(add), &set(remove));
+        doc.to_string()
+    }
+
+    #[test]
+    fn create_empty_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("Cargo.toml");
+        std::fs::write(&manifest_path, "[package]\nname = \"mycrate\"\n").unwrap();
+
+        create_list(&manifest_path, "allowed", &BTreeSet::new()).unwrap();
+
+        assert!(has_table(&manifest_path).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&manifest_path).unwrap(),
+            "\
+[package]
+name = \"mycrate\"
+
+[package.metadata.cargo-public-api-crates]
+allowed = []
+"
+        );
+    }
+
+    #[test]
+    fn remove_last_commented_entry() {
+        let toml = "\
+allowed = [
+    \"bytes\", # shared buffer type
+    # needed for `Client::request`
+    \"http\",
+]
+";
+        assert_eq!(
+            update(toml, &[], &["http"]),
+            "\
+allowed = [
+    \"bytes\", # shared buffer type
+]
+"
+        );
+    }
+
+    #[test]
+    fn insert_into_single_line_array() {
+        assert_eq!(
+            update("allowed = [\"bytes\", \"tokio\"]\n", &["http"], &[]),
+            "allowed = [\"bytes\", \"http\", \"tokio\"]\n"
+        );
+    }
+
+    #[test]
+    fn insert_into_multi_line_array() {
+        let toml = "\
+allowed = [
+  \"bytes\",
+  # runtime
+  \"tokio\",
+]
+";
+        assert_eq!(
+            update(toml, &["http", "serde"], &[]),
+            "\
+allowed = [
+  \"bytes\",
+  \"http\",
+  \"serde\",
+  # runtime
+  \"tokio\",
+]
+"
+        );
+    }
+}
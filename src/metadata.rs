@@ -0,0 +1,101 @@
+use anyhow::{ensure, Context, Result};
+use semver::Version;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Run `cargo metadata` for the workspace containing `manifest_path`.
+///
+/// The dependency graph is resolved with all features so it includes every optional dependency
+/// that could end up in the public API.
+pub fn run(manifest_path: Option<&Path>) -> Result<Metadata> {
+    let mut cmd = Command::new("cargo");
+    cmd.args(["metadata", "--format-version", "1", "--all-features"]);
+    if let Some(manifest_path) = manifest_path {
+        cmd.arg("--manifest-path").arg(manifest_path);
+    }
+    cmd.stderr(Stdio::inherit());
+
+    let output = cmd.output().context("failed to run `cargo metadata`")?;
+    ensure!(output.status.success(), "`cargo metadata` failed");
+
+    serde_json::from_slice(&output.stdout).context("failed to parse `cargo metadata` output")
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Metadata {
+    pub packages: Vec<Package>,
+    pub workspace_members: Vec<String>,
+    pub target_directory: PathBuf,
+    /// `[workspace.metadata]`.
+    pub metadata: Option<ToolMetadata>,
+    pub resolve: Option<Resolve>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Package {
+    pub id: String,
+    pub name: String,
+    pub version: Version,
+    pub source: Option<String>,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<Target>,
+    pub features: BTreeMap<String, Vec<String>>,
+    /// `[package.metadata]`.
+    pub metadata: Option<ToolMetadata>,
+}
+
+impl Package {
+    pub fn lib_target(&self) -> Option<&Target> {
+        self.targets
+            .iter()
+            .find(|target| target.kind.iter().any(|kind| kind == "lib"))
+    }
+}
+
+/// The parts of `[package.metadata]` or `[workspace.metadata]` that matter before building.
+#[derive(Deserialize, Debug)]
+pub struct ToolMetadata {
+    #[serde(rename = "cargo-public-api-crates")]
+    pub cargo_public_api_crates: Option<ToolConfig>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ToolConfig {
+    pub toolchain: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Target {
+    pub name: String,
+    pub kind: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Resolve {
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Node {
+    pub id: String,
+    pub deps: Vec<NodeDep>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NodeDep {
+    /// Name the dependency is used under, which is the dependency's key in Cargo.toml with `-`
+    /// replaced by `_`.
+    pub name: String,
+    pub pkg: String,
+    pub dep_kinds: Vec<DepKind>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DepKind {
+    /// `None` for normal dependencies, otherwise `dev` or `build`.
+    pub kind: Option<String>,
+}
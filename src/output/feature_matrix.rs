@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{
+    for_each_package, write_json,
+    writer::{LendingIterator, Writer},
+    Format,
+};
+use crate::feature_matrix::{Configurations, FeatureMatrix};
+
+#[derive(Serialize)]
+struct Package {
+    name: String,
+    crates: BTreeMap<String, Configurations>,
+    /// Configurations whose docs failed to build.
+    failed_configurations: Configurations,
+}
+
+/// Print which feature configurations expose each crate.
+pub fn run(reports: Vec<(String, FeatureMatrix)>, format: Format) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+
+    if let Format::Json = format {
+        let packages = reports
+            .into_iter()
+            .map(|(name, matrix)| Package {
+                name,
+                crates: matrix.crates,
+                failed_configurations: matrix.failed,
+            })
+            .collect();
+        return write_json(packages, &mut stdout);
+    }
+
+    for_each_package(reports, &mut stdout, |_, matrix, out| {
+        let failed = (!matrix.failed.is_empty()).then_some(("build failed", &matrix.failed));
+        let sections = matrix
+            .crates
+            .iter()
+            .map(|(name, configurations)| (name.as_str(), configurations))
+            .chain(failed);
+
+        for (idx, (name, configurations)) in sections.enumerate() {
+            if idx != 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{name}")?;
+
+            let mut w = Writer::new(4, out);
+            let mut iter = w.iter(configurations.labels());
+            while let Some((mut w, configuration)) = iter.next() {
+                w.write_line(format_args!("{configuration}"))?;
+            }
+        }
+
+        Ok(())
+    })
+}
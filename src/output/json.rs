@@ -0,0 +1,161 @@
+use std::{io::Write, path::PathBuf};
+
+use anyhow::{Context, Result};
+use rustdoc_types::Id;
+use serde::Serialize;
+
+use crate::{analyze::AnalyzeOutput, visit::Exposure};
+
+#[derive(Serialize)]
+struct Package {
+    name: String,
+    crates: Vec<Crate>,
+}
+
+#[derive(Serialize)]
+struct Crate {
+    name: String,
+    crate_id: u32,
+    version: Option<String>,
+    /// Name of the package the crate comes from, if it was found in the dependency graph.
+    package: Option<String>,
+    /// Name of the dependency in Cargo.toml, if it's a direct dependency.
+    dependency: Option<String>,
+    /// Whether the crate is in the dependency graph but isn't a direct dependency.
+    transitive: bool,
+    /// Where the package comes from, as reported by `cargo metadata`. `None` for path
+    /// dependencies and crates that couldn't be found in the dependency graph.
+    source: Option<String>,
+    /// Whether the crate is only exposed through trait impls, rather than in signatures.
+    trait_impl_only: bool,
+    /// Whether the crate is only exposed through exported macros.
+    macro_only: bool,
+    /// Whether the crate is only exposed through `#[doc(hidden)]` items.
+    hidden_only: bool,
+    /// Modules from the crate that are re-exported in full, including the crate root.
+    reexported_modules: Vec<String>,
+    items: Vec<Item>,
+}
+
+#[derive(Serialize)]
+struct Item {
+    path: String,
+    usages: Vec<Usage>,
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+struct Usage {
+    file: Option<PathBuf>,
+    line: Option<usize>,
+    column: Option<usize>,
+    end_line: Option<usize>,
+    end_column: Option<usize>,
+    /// The public item in the analyzed crate that uses the external item, e.g. `fn`.
+    item_kind: &'static str,
+    /// Path of that item from the crate root, e.g. `mycrate::Client::send`.
+    item_path: String,
+    /// Whether the usage comes from a trait impl rather than a signature.
+    trait_impl: bool,
+    /// Where in the item's signature the external item appears, e.g. `return-type`.
+    exposure: Exposure,
+    /// Whether the item is only reachable through `#[doc(hidden)]` items.
+    hidden: bool,
+}
+
+pub(super) fn run(reports: Vec<(String, AnalyzeOutput)>, out: &mut dyn Write) -> Result<()> {
+    let packages = reports
+        .into_iter()
+        .map(|(name, analyze_output)| {
+            Ok(Package {
+                name,
+                crates: crates(analyze_output)?,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    super::write_json::<Package>(packages, out)
+}
+
+fn crates(analyze_output: AnalyzeOutput) -> Result<Vec<Crate>> {
+    let reexported_modules = analyze_output.reexported_modules()?;
+
+    let mut crates = analyze_output
+        .crate_id_to_public_item
+        .iter()
+        .map(|(crate_id, ids)| {
+            let external_crate = analyze_output
+                .krate
+                .external_crates
+                .get(crate_id)
+                .context("crate missing")?;
+            let crate_ref = analyze_output.crate_ref(*crate_id)?;
+
+            let mut items = ids
+                .iter()
+                .map(|id| item(&analyze_output, id))
+                .collect::<Result<Vec<_>>>()?;
+            items.sort_by(|a, b| a.path.cmp(&b.path));
+
+            Ok(Crate {
+                name: external_crate.name.clone(),
+                crate_id: *crate_id,
+                version: crate_ref.version.as_ref().map(ToString::to_string),
+                package: crate_ref.package.clone(),
+                dependency: crate_ref.dependency_key.clone(),
+                transitive: crate_ref.is_transitive(),
+                source: analyze_output
+                    .dependencies
+                    .get(crate_id)
+                    .and_then(|dependency| dependency.source.clone()),
+                trait_impl_only: analyze_output.trait_impl_only(*crate_id),
+                macro_only: analyze_output.macro_only(*crate_id),
+                hidden_only: analyze_output.hidden_only(*crate_id),
+                reexported_modules: reexported_modules
+                    .get(&crate_ref)
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect(),
+                items,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    crates.sort_by_key(|krate| krate.crate_id);
+
+    Ok(crates)
+}
+
+fn item(analyze_output: &AnalyzeOutput, id: &Id) -> Result<Item> {
+    let path = analyze_output
+        .krate
+        .paths
+        .get(id)
+        .context("path missing")?
+        .path
+        .join("::");
+
+    let mut usages = analyze_output
+        .id_to_usages
+        .get(id)
+        .into_iter()
+        .flatten()
+        .map(|usage| {
+            let span = usage.span.as_ref();
+            Usage {
+                file: span.map(|span| span.filename.clone()),
+                line: span.map(|span| span.begin.0),
+                column: span.map(|span| span.begin.1),
+                end_line: span.map(|span| span.end.0),
+                end_column: span.map(|span| span.end.1),
+                item_kind: analyze_output.item_kind(&usage.item),
+                item_path: analyze_output.public_path(&usage.item),
+                trait_impl: usage.trait_impl,
+                exposure: usage.exposure,
+                hidden: usage.hidden,
+            }
+        })
+        .collect::<Vec<_>>();
+    usages.sort();
+
+    Ok(Item { path, usages })
+}
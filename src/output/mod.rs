@@ -1,30 +1,126 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashSet},
     io::Write,
 };
 
-use crate::analyze::AnalyzeOutput;
+use crate::analyze::{AnalyzeOutput, CrateRef, Hidden, Usage};
 use anyhow::{Context, Result};
+use clap::ValueEnum;
 use itertools::{Itertools, Position};
-use rustdoc_types::{Crate, Id, Span};
+use rustdoc_types::Id;
+use serde::Serialize;
 
 use self::writer::{LendingIterator, Writer};
 
+pub mod feature_matrix;
+mod json;
+pub mod transitive;
 mod writer;
 
-pub fn run(analyze_output: AnalyzeOutput) -> Result<()> {
-    let AnalyzeOutput {
-        krate,
-        crate_id_to_public_item,
-        id_to_usages,
-    } = analyze_output;
+/// Version of the JSON output of every report. Bumped whenever a field is removed or changes
+/// meaning. Adding fields doesn't bump it.
+const SCHEMA_VERSION: u32 = 1;
 
-    let mut crate_id_to_public_item = crate_id_to_public_item.into_iter().collect::<Vec<_>>();
-    crate_id_to_public_item.sort_by(|(a, _), (b, _)| a.cmp(b));
+#[derive(Serialize)]
+struct Report<P> {
+    schema_version: u32,
+    packages: Vec<P>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default)]
+pub enum Format {
+    /// Human readable tree.
+    #[default]
+    Tree,
+    /// Machine readable JSON with a versioned schema.
+    Json,
+}
 
+/// Print the crates in the public API of each package.
+///
+/// In the tree format reports are headed with the package name when there is more than one.
+pub fn run(reports: Vec<(String, AnalyzeOutput)>, format: Format) -> Result<()> {
     let mut stdout = std::io::stdout().lock();
 
-    for item in crate_id_to_public_item.into_iter().with_position() {
+    if let Format::Json = format {
+        return json::run(reports, &mut stdout);
+    }
+
+    for_each_package(reports, &mut stdout, |_, analyze_output, out| {
+        output_report(analyze_output, out)
+    })
+}
+
+/// Write the JSON report of `packages`.
+fn write_json<P: Serialize>(packages: Vec<P>, out: &mut dyn Write) -> Result<()> {
+    let report = Report {
+        schema_version: SCHEMA_VERSION,
+        packages,
+    };
+    serde_json::to_writer_pretty(&mut *out, &report)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Output each package's report in the tree format, headed with the package name when there is
+/// more than one.
+fn for_each_package<T>(
+    reports: Vec<(String, T)>,
+    out: &mut dyn Write,
+    mut output: impl FnMut(&str, T, &mut dyn Write) -> Result<()>,
+) -> Result<()> {
+    let show_package = reports.len() > 1;
+
+    for (idx, (package, report)) in reports.into_iter().enumerate() {
+        if show_package {
+            if idx != 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "=== {package} ===")?;
+        }
+
+        output(&package, report, out)?;
+    }
+
+    Ok(())
+}
+
+fn output_report(analyze_output: AnalyzeOutput, out: &mut dyn Write) -> Result<()> {
+    let mut crate_id_to_public_item = analyze_output
+        .crate_id_to_public_item
+        .iter()
+        .collect::<Vec<_>>();
+    crate_id_to_public_item.sort_by_key(|(crate_id, _)| **crate_id);
+
+    let (hidden_crates, crates): (Vec<_>, Vec<_>) =
+        crate_id_to_public_item
+            .into_iter()
+            .partition(|(crate_id, _)| {
+                analyze_output.hidden == Hidden::Separate && analyze_output.hidden_only(**crate_id)
+            });
+
+    output_crates(&crates, &analyze_output, out)?;
+
+    if !hidden_crates.is_empty() {
+        if !crates.is_empty() {
+            writeln!(out)?;
+        }
+        writeln!(out, "Only exposed through #[doc(hidden)] items:")?;
+        writeln!(out)?;
+        output_crates(&hidden_crates, &analyze_output, out)?;
+    }
+
+    Ok(())
+}
+
+fn output_crates(
+    crates: &[(&u32, &HashSet<Id>)],
+    analyze_output: &AnalyzeOutput,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let reexported_modules = analyze_output.reexported_modules()?;
+
+    for item in crates.iter().with_position() {
         let (crate_id, ids, last) = match item {
             Position::First((crate_id, ids)) | Position::Middle((crate_id, ids)) => {
                 (crate_id, ids, false)
@@ -34,10 +130,10 @@ pub fn run(analyze_output: AnalyzeOutput) -> Result<()> {
             }
         };
 
-        output_crate(crate_id, ids, &krate, &id_to_usages, &mut stdout)?;
+        output_crate(**crate_id, ids, &reexported_modules, analyze_output, out)?;
 
         if !last {
-            writeln!(&mut stdout)?;
+            writeln!(out)?;
         }
     }
 
@@ -46,71 +142,71 @@ pub fn run(analyze_output: AnalyzeOutput) -> Result<()> {
 
 fn output_crate(
     crate_id: u32,
-    ids: HashSet<Id>,
-    krate: &Crate,
-    id_to_usages: &HashMap<Id, HashSet<Span>>,
+    ids: &HashSet<Id>,
+    reexported_modules: &BTreeMap<CrateRef, BTreeSet<String>>,
+    analyze_output: &AnalyzeOutput,
     out: &mut dyn Write,
 ) -> anyhow::Result<()> {
-    let external_crate = krate
-        .external_crates
-        .get(&crate_id)
-        .context("crate missing")?;
-    writeln!(out, "{}", external_crate.name)?;
+    let krate = &analyze_output.krate;
+    let crate_ref = analyze_output.crate_ref(crate_id)?;
+    let mut header = crate_ref.to_string();
+    if crate_ref.is_transitive() {
+        header.push_str(" (not a direct dependency)");
+    }
+    if analyze_output.trait_impl_only(crate_id) {
+        header.push_str(" (trait impls only)");
+    }
+    if analyze_output.macro_only(crate_id) {
+        header.push_str(" (macros only)");
+    }
+    if let Some(modules) = reexported_modules.get(&crate_ref) {
+        header.push_str(&format!(
+            " (re-exported in full: {})",
+            modules.iter().join(", ")
+        ));
+    }
+    writeln!(out, "{header}")?;
 
-    let mut ids = ids.into_iter().collect::<Vec<_>>();
+    let mut ids = ids.iter().collect::<Vec<_>>();
     ids.sort_by(|a, b| a.0.cmp(&b.0));
 
     let mut w = Writer::new(4, out);
 
     let mut iter = w.iter(ids);
     while let Some((mut w, id)) = iter.next() {
-        let item = krate.paths.get(&id).context("path missing")?;
+        let item = krate.paths.get(id).context("path missing")?;
         let name = item.path.join("::");
         w.write_line(format_args!("{name}"))?;
 
-        if let Some(spans) = id_to_usages.get(&id) {
-            let mut spans = spans.iter().collect::<Vec<_>>();
-            spans.sort_by(|a, b| {
-                (&a.filename, a.begin.0, a.begin.1, a.end.0, a.end.1).cmp(&(
-                    &b.filename,
-                    b.begin.0,
-                    b.begin.1,
-                    b.end.0,
-                    b.end.1,
-                ))
-            });
+        if let Some(usages) = analyze_output.id_to_usages.get(id) {
+            let mut usages = usages.iter().collect::<Vec<_>>();
+            usages.sort_by_key(|usage| sort_key(usage));
+            let usages = usages
+                .into_iter()
+                .map(|usage| describe_usage(usage, analyze_output))
+                .collect::<Vec<_>>();
 
             let max_show = 3;
-            if spans.len() <= max_show {
-                let mut iter = w.iter(spans);
-                while let Some((mut w, span)) = iter.next() {
-                    w.write_line(format_args!(
-                        "{}:{}:{}",
-                        span.filename.display(),
-                        span.begin.0,
-                        span.begin.1
-                    ))?;
+            if usages.len() <= max_show {
+                let mut iter = w.iter(&usages);
+                while let Some((mut w, usage)) = iter.next() {
+                    w.write_line(format_args!("{usage}"))?;
                 }
             } else {
                 let mut iter = w.iter(
-                    spans
+                    usages
                         .iter()
                         .take(max_show)
                         .map(Some)
                         .chain(std::iter::once(None)),
                 );
-                while let Some((mut w, span)) = iter.next() {
-                    match span {
-                        Some(span) => {
-                            w.write_line(format_args!(
-                                "{}:{}:{}",
-                                span.filename.display(),
-                                span.begin.0,
-                                span.begin.1
-                            ))?;
+                while let Some((mut w, usage)) = iter.next() {
+                    match usage {
+                        Some(usage) => {
+                            w.write_line(format_args!("{usage}"))?;
                         }
                         None => {
-                            w.write_line(format_args!("and {} more...", spans.len() - max_show))?;
+                            w.write_line(format_args!("and {} more...", usages.len() - max_show))?;
                         }
                     }
                 }
@@ -120,3 +216,32 @@ fn output_crate(
 
     Ok(())
 }
+
+fn sort_key(usage: &Usage) -> impl Ord + '_ {
+    (
+        usage
+            .span
+            .as_ref()
+            .map(|span| (&span.filename, span.begin, span.end)),
+        &usage.item.0,
+        usage.exposure,
+    )
+}
+
+/// E.g. `return type of fn mycrate::Client::send (src/lib.rs:120:1)`.
+fn describe_usage(usage: &Usage, analyze_output: &AnalyzeOutput) -> String {
+    let item = format!(
+        "{} {}",
+        usage.exposure.label(),
+        analyze_output.describe_item(&usage.item)
+    );
+    match &usage.span {
+        Some(span) => format!(
+            "{item} ({}:{}:{})",
+            span.filename.display(),
+            span.begin.0,
+            span.begin.1
+        ),
+        None => item,
+    }
+}
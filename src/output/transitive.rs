@@ -0,0 +1,55 @@
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{
+    for_each_package, write_json,
+    writer::{LendingIterator, Writer},
+    Format,
+};
+use crate::transitive::Closure;
+
+#[derive(Serialize)]
+struct Package {
+    name: String,
+    crates: Closure,
+}
+
+/// Print every crate users are coupled to and what couples them to it.
+pub fn run(reports: Vec<(String, Closure)>, format: Format) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+
+    if let Format::Json = format {
+        let packages = reports
+            .into_iter()
+            .map(|(name, crates)| Package { name, crates })
+            .collect();
+        return write_json(packages, &mut stdout);
+    }
+
+    for_each_package(reports, &mut stdout, |package, closure, out| {
+        for (idx, (name, coupling)) in closure.into_iter().enumerate() {
+            if idx != 0 {
+                writeln!(out)?;
+            }
+            if coupling.transitive {
+                writeln!(out, "{name} (not a direct dependency)")?;
+            } else {
+                writeln!(out, "{name}")?;
+            }
+
+            let mut reasons = Vec::new();
+            if coupling.direct {
+                reasons.push(format!("exposed by {package}"));
+            }
+            reasons.extend(coupling.via.iter().map(|via| format!("exposed by {via}")));
+
+            let mut w = Writer::new(4, out);
+            let mut iter = w.iter(reasons);
+            while let Some((mut w, reason)) = iter.next() {
+                w.write_line(format_args!("{reason}"))?;
+            }
+        }
+
+        Ok(())
+    })
+}
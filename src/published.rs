@@ -0,0 +1,155 @@
+use std::{
+    env,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use flate2::read::GzDecoder;
+use semver::Version;
+use tempfile::TempDir;
+
+use crate::manifest;
+
+/// A copy of a published crate in a temporary directory, so it can be built without touching the
+/// original. The directory is removed on drop.
+pub struct Scratch {
+    _dir: TempDir,
+    pub manifest_path: PathBuf,
+}
+
+/// Unpack the crate given to `--crate`, which is a `.crate` file, an unpacked crate directory, or
+/// `name@version` of a crate in the local registry cache.
+pub fn unpack(spec: &str) -> Result<Scratch> {
+    let dir = tempfile::Builder::new()
+        .prefix("cargo-public-api-crates-")
+        .tempdir()
+        .context("failed to create temporary directory")?;
+
+    let path = Path::new(spec);
+    let package_dir = if path.is_dir() {
+        let package_dir = dir
+            .path()
+            .join(path.file_name().unwrap_or("crate".as_ref()));
+        copy_dir(path, &package_dir)?;
+        package_dir
+    } else if path.is_file() {
+        unpack_crate_file(path, dir.path())?
+    } else if let Some((name, version)) = spec.split_once('@') {
+        let version = version
+            .parse::<Version>()
+            .with_context(|| format!("invalid version in `{spec}`"))?;
+        let (path, is_crate_file) = find_in_registry(name, &version)?;
+        if is_crate_file {
+            unpack_crate_file(&path, dir.path())?
+        } else {
+            let package_dir = dir.path().join(format!("{name}-{version}"));
+            copy_dir(&path, &package_dir)?;
+            package_dir
+        }
+    } else {
+        bail!("`{spec}` isn't a file, a directory, or `name@version`");
+    };
+
+    let manifest_path = package_dir.join("Cargo.toml");
+    ensure!(
+        manifest_path.exists(),
+        "`{spec}` doesn't contain a Cargo.toml"
+    );
+    // published manifests don't have a `[workspace]`, without one cargo would look for a
+    // workspace in the parent directories
+    manifest::make_workspace_root(&manifest_path)?;
+
+    Ok(Scratch {
+        _dir: dir,
+        manifest_path,
+    })
+}
+
+/// Unpack a `.crate` file, which is a gzipped tarball with a `name-version` directory, into
+/// `dest`.
+fn unpack_crate_file(path: &Path, dest: &Path) -> Result<PathBuf> {
+    let file = File::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(dest)
+        .with_context(|| format!("failed to unpack {}", path.display()))?;
+
+    let entries = std::fs::read_dir(dest)?.collect::<std::io::Result<Vec<_>>>()?;
+    match entries.as_slice() {
+        [entry] if entry.path().is_dir() => Ok(entry.path()),
+        _ => bail!(
+            "{} doesn't contain a single package directory",
+            path.display()
+        ),
+    }
+}
+
+/// Find `name-version` in `~/.cargo/registry`, preferring the unpacked sources over the `.crate`
+/// file. Returns the path and whether it's a `.crate` file.
+fn find_in_registry(name: &str, version: &Version) -> Result<(PathBuf, bool)> {
+    let registry = cargo_home()?.join("registry");
+    let package = format!("{name}-{version}");
+
+    // there's a directory per registry index, e.g. `index.crates.io-6f17d22bba15001f`
+    let subdirs = |dir: &str| -> Vec<PathBuf> {
+        std::fs::read_dir(registry.join(dir))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .collect()
+    };
+
+    if let Some(path) = subdirs("src")
+        .into_iter()
+        .map(|index| index.join(&package))
+        .find(|path| path.is_dir())
+    {
+        return Ok((path, false));
+    }
+    if let Some(path) = subdirs("cache")
+        .into_iter()
+        .map(|index| index.join(format!("{package}.crate")))
+        .find(|path| path.is_file())
+    {
+        return Ok((path, true));
+    }
+
+    bail!(
+        "`{name}@{version}` not found in {}. Add it as a dependency and run `cargo fetch` to \
+        download it",
+        registry.display()
+    )
+}
+
+fn cargo_home() -> Result<PathBuf> {
+    if let Some(cargo_home) = env::var_os("CARGO_HOME") {
+        return Ok(PathBuf::from(cargo_home));
+    }
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".cargo"))
+        .context("failed to find the cargo home directory, set `CARGO_HOME`")
+}
+
+/// Copy a package directory, leaving out build output and version control.
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to).with_context(|| format!("failed to create {}", to.display()))?;
+    for entry in
+        std::fs::read_dir(from).with_context(|| format!("failed to read {}", from.display()))?
+    {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        if file_name == "target" || file_name == ".git" {
+            continue;
+        }
+        let (from, to) = (entry.path(), to.join(&file_name));
+        if entry.file_type()?.is_dir() {
+            copy_dir(&from, &to)?;
+        } else {
+            std::fs::copy(&from, &to)
+                .with_context(|| format!("failed to copy {}", from.display()))?;
+        }
+    }
+    Ok(())
+}
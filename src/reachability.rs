@@ -0,0 +1,153 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use rustdoc_types::{Crate, Id, Item, ItemEnum, StructKind, VariantKind};
+
+/// Find the items that can be named from outside the crate, and the public path of each.
+///
+/// Starts at the crate root and follows module items, re-exports, and the children of each
+/// reachable item (fields, variants, impls, and associated items). Items are visited breadth
+/// first so the shortest path is the one that's recorded.
+///
+/// Impls don't have a name of their own, so they get the path of the type or trait they're
+/// reached from.
+///
+/// Also returns the items that are hidden, meaning they're only reachable through items marked
+/// `#[doc(hidden)]`. Visible paths are searched first so an item that's re-exported from a hidden
+/// module isn't hidden.
+pub fn run(krate: &Crate) -> (HashMap<Id, Vec<String>>, HashSet<Id>) {
+    let root_path = krate
+        .index
+        .get(&krate.root)
+        .and_then(|root| root.name.clone());
+    from_items(krate, vec![(krate.root.clone(), Vec::from_iter(root_path))])
+}
+
+/// Like [`run`] but starts at `items`, each with its public path, rather than the crate root.
+pub fn from_items(
+    krate: &Crate,
+    items: Vec<(Id, Vec<String>)>,
+) -> (HashMap<Id, Vec<String>>, HashSet<Id>) {
+    let mut reachable = HashMap::new();
+    let mut hidden = HashSet::new();
+
+    let mut queue = VecDeque::from(items);
+    let mut hidden_queue = VecDeque::new();
+
+    while let Some((id, path, is_hidden)) = queue
+        .pop_front()
+        .map(|(id, path)| (id, path, false))
+        .or_else(|| hidden_queue.pop_front().map(|(id, path)| (id, path, true)))
+    {
+        let Some(item) = krate.index.get(&id) else { continue };
+
+        // items from external crates are reported, not searched
+        if krate.external_crates.contains_key(&item.crate_id) {
+            continue;
+        }
+
+        if reachable.contains_key(&id) {
+            continue;
+        }
+
+        if !is_hidden && is_doc_hidden(item) {
+            hidden_queue.push_back((id, path));
+            continue;
+        }
+
+        if is_hidden {
+            push_children(krate, item, &path, &mut hidden_queue);
+            hidden.insert(id.clone());
+        } else {
+            push_children(krate, item, &path, &mut queue);
+        }
+        reachable.insert(id, path);
+    }
+
+    (reachable, hidden)
+}
+
+fn is_doc_hidden(item: &Item) -> bool {
+    item.attrs
+        .iter()
+        .any(|attr| attr.replace(' ', "") == "#[doc(hidden)]")
+}
+
+fn push_children(
+    krate: &Crate,
+    item: &Item,
+    path: &[String],
+    queue: &mut VecDeque<(Id, Vec<String>)>,
+) {
+    let mut push = |ids: &mut dyn Iterator<Item = &Id>| {
+        for id in ids {
+            let Some(child) = krate.index.get(id) else { continue };
+            queue.push_back((id.clone(), child_path(path, child)));
+        }
+    };
+
+    match &item.inner {
+        ItemEnum::Module(module) => push(&mut module.items.iter()),
+        // the imported item is nameable at the path of the import
+        ItemEnum::Import(import) => queue.extend(import.id.clone().map(|id| (id, path.to_vec()))),
+        ItemEnum::Struct(struct_) => {
+            match &struct_.kind {
+                StructKind::Unit => {}
+                StructKind::Tuple(fields) => push(&mut fields.iter().flatten()),
+                StructKind::Plain {
+                    fields,
+                    fields_stripped: _,
+                } => push(&mut fields.iter()),
+            }
+            push(&mut struct_.impls.iter());
+        }
+        ItemEnum::Union(union_) => {
+            push(&mut union_.fields.iter());
+            push(&mut union_.impls.iter());
+        }
+        ItemEnum::Enum(enum_) => {
+            push(&mut enum_.variants.iter());
+            push(&mut enum_.impls.iter());
+        }
+        ItemEnum::Variant(variant) => match &variant.kind {
+            VariantKind::Plain => {}
+            VariantKind::Tuple(fields) => push(&mut fields.iter().flatten()),
+            VariantKind::Struct {
+                fields,
+                fields_stripped: _,
+            } => push(&mut fields.iter()),
+        },
+        ItemEnum::Trait(trait_) => {
+            push(&mut trait_.items.iter());
+            push(&mut trait_.implementations.iter());
+        }
+        ItemEnum::Impl(impl_) => push(&mut impl_.items.iter()),
+        ItemEnum::Primitive(primitive) => push(&mut primitive.impls.iter()),
+
+        // these don't have children
+        ItemEnum::ExternCrate { .. } => {}
+        ItemEnum::StructField(_) => {}
+        ItemEnum::Function(_) => {}
+        ItemEnum::TraitAlias(_) => {}
+        ItemEnum::TypeAlias(_) => {}
+        ItemEnum::OpaqueTy(_) => {}
+        ItemEnum::Constant(_) => {}
+        ItemEnum::Static(_) => {}
+        ItemEnum::ForeignType => {}
+        ItemEnum::Macro(_) => {}
+        ItemEnum::ProcMacro(_) => {}
+        ItemEnum::AssocConst { .. } => {}
+        ItemEnum::AssocType { .. } => {}
+    }
+}
+
+fn child_path(parent_path: &[String], child: &Item) -> Vec<String> {
+    let mut path = parent_path.to_vec();
+    match &child.inner {
+        ItemEnum::Impl(_) => {}
+        // a glob import brings the module's items into the importing module
+        ItemEnum::Import(import) if import.glob => {}
+        ItemEnum::Import(import) => path.push(import.name.clone()),
+        _ => path.extend(child.name.clone()),
+    }
+    path
+}
@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    analyze::{self, AnalyzeOutput, Hidden},
+    build_docs::{self, Features},
+    dependencies::Dependency,
+    workspace::Member,
+};
+
+/// How users of the analyzed crate are coupled to a crate.
+#[derive(Serialize, Debug, Default)]
+pub struct Coupling {
+    /// Whether the analyzed crate exposes the crate itself.
+    pub direct: bool,
+    /// Crates whose exposed items in turn expose the crate.
+    pub via: BTreeSet<String>,
+    /// Whether the crate is in the dependency graph but isn't a direct dependency.
+    pub transitive: bool,
+    /// Paths of the items from the crate that users can reach.
+    pub items: BTreeSet<String>,
+}
+
+/// Map from crate name and version to how users are coupled to it.
+pub type Closure = BTreeMap<String, Coupling>;
+
+/// Follow the items `member` exposes from its dependencies into the public APIs of those
+/// dependencies, and so on, to find every crate that users of `member` are coupled to.
+///
+/// The docs of each exposed dependency are built, and only the items `member` exposes from it are
+/// analyzed, along with what's reachable from them. Dependencies are built with the features
+/// that `features` enables for them. Crates that aren't in the dependency graph, such as `std`,
+/// are reported but not followed.
+pub fn run(
+    member: &Member,
+    analyze_output: AnalyzeOutput,
+    features: &Features,
+    include_std: bool,
+    hidden: Hidden,
+) -> Result<Closure> {
+    let mut closure = Closure::new();
+    let mut queue = VecDeque::new();
+    // items analyzed so far by package, so a crate is only analyzed again if it's reached with
+    // items that weren't analyzed before
+    let mut analyzed = HashMap::<String, BTreeSet<String>>::new();
+
+    add_exposed(&analyze_output, None, &mut closure, &mut queue)?;
+
+    while let Some((dependency, via, paths)) = queue.pop_front() {
+        let spec = format!("{}@{}", dependency.name, dependency.version);
+        let analyzed = analyzed.entry(spec.clone()).or_default();
+        let paths = paths.difference(analyzed).cloned().collect::<BTreeSet<_>>();
+        if paths.is_empty() {
+            continue;
+        }
+        analyzed.extend(paths.iter().cloned());
+
+        let analyze_dependency = || {
+            let doc_json_path = build_docs::run_dependency(member, &dependency, features, hidden)?;
+            // the docs of registry packages name their source files by absolute paths, those of
+            // path dependencies are found from the member's directory like its own
+            analyze::run_from_items(
+                &doc_json_path,
+                member.manifest_dir(),
+                &paths,
+                &member.dependencies,
+                include_std,
+                hidden,
+            )
+        };
+        let analyze_output =
+            analyze_dependency().with_context(|| format!("failed to analyze `{spec}`"))?;
+        add_exposed(&analyze_output, Some(&via), &mut closure, &mut queue)?;
+    }
+
+    Ok(closure)
+}
+
+/// Add the crates in `analyze_output` to `closure`, and queue the ones from the dependency graph
+/// to be followed.
+fn add_exposed(
+    analyze_output: &AnalyzeOutput,
+    via: Option<&str>,
+    closure: &mut Closure,
+    queue: &mut VecDeque<(Dependency, String, BTreeSet<String>)>,
+) -> Result<()> {
+    for (crate_id, ids) in &analyze_output.crate_id_to_public_item {
+        let crate_ref = analyze_output.crate_ref(*crate_id)?;
+        let name = crate_ref.to_string();
+        let paths = ids
+            .iter()
+            .map(|id| {
+                let item = analyze_output.krate.paths.get(id).context("path missing")?;
+                Ok(item.path.join("::"))
+            })
+            .collect::<Result<BTreeSet<_>>>()?;
+
+        let coupling = closure.entry(name.clone()).or_default();
+        match via {
+            Some(via) => {
+                coupling.via.insert(via.to_owned());
+            }
+            None => coupling.direct = true,
+        }
+        coupling.transitive = crate_ref.is_transitive();
+        coupling.items.extend(paths.iter().cloned());
+
+        if let Some(dependency) = analyze_output.dependencies.get(crate_id) {
+            queue.push_back((dependency.clone(), name, paths));
+        }
+    }
+
+    Ok(())
+}
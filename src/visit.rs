@@ -3,24 +3,84 @@
 use rustdoc_types::{
     Constant, DynTrait, Enum, FnDecl, Function, FunctionPointer, GenericArg, GenericArgs,
     GenericBound, GenericParamDef, GenericParamDefKind, Generics, Impl, Import, Item, ItemEnum,
-    OpaqueTy, Path, PolyTrait, Static, Struct, StructKind, Term, Trait, TraitAlias, Type,
+    OpaqueTy, Path, PolyTrait, Span, Static, Struct, StructKind, Term, Trait, TraitAlias, Type,
     TypeAlias, TypeBinding, TypeBindingKind, Union, WherePredicate,
 };
+use serde::Serialize;
+
+/// Where in an item's signature a path appears.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Exposure {
+    /// A function argument.
+    Argument,
+    /// A function's return type.
+    ReturnType,
+    /// The type of a struct, union, or variant field.
+    Field,
+    /// The type of a const, static, or type alias.
+    Type,
+    /// A bound on a generic parameter or `impl Trait`, or a generic parameter's default.
+    Bound,
+    /// A where clause.
+    WhereClause,
+    /// A trait's supertraits.
+    Supertrait,
+    /// The type an impl is for.
+    ImplTarget,
+    /// The trait an impl implements.
+    ImplTrait,
+    /// The value of an associated type.
+    AssocType,
+    /// A `use` declaration or `extern crate`.
+    Import,
+    /// The module or enum a glob import re-exports everything from.
+    GlobImport,
+    /// The body of a `macro_rules!` macro.
+    Macro,
+}
+
+impl Exposure {
+    /// Describes the exposure relative to the item, e.g. `return type of fn mycrate::f`.
+    pub fn label(self) -> &'static str {
+        match self {
+            Exposure::Argument => "argument of",
+            Exposure::ReturnType => "return type of",
+            Exposure::Field => "type of",
+            Exposure::Type => "type of",
+            Exposure::Bound => "bound in",
+            Exposure::WhereClause => "where clause of",
+            Exposure::Supertrait => "supertrait of",
+            Exposure::ImplTarget => "self type of",
+            Exposure::ImplTrait => "trait of",
+            Exposure::AssocType => "value of",
+            Exposure::Import => "re-exported by",
+            Exposure::GlobImport => "re-exported by",
+            Exposure::Macro => "expansion of",
+        }
+    }
+}
 
 #[allow(unused_variables)]
 pub trait Visitor {
     #[inline]
-    fn visit_path(&mut self, path: &Path) {}
+    fn visit_path(&mut self, path: &Path, exposure: Exposure) {}
 
     #[inline]
     fn visit_import(&mut self, import: &Import) {}
+
+    #[inline]
+    fn visit_extern_crate(&mut self, name: &str) {}
+
+    #[inline]
+    fn visit_macro(&mut self, source: &str, span: Option<&Span>) {}
 }
 
 pub fn visit_item(item: &Item, v: &mut impl Visitor) {
     match &item.inner {
         ItemEnum::Function(fun) => visit_function(fun, v),
         ItemEnum::Struct(struct_) => visit_struct(struct_, v),
-        ItemEnum::StructField(field_type) => visit_type(field_type, v),
+        ItemEnum::StructField(field_type) => visit_type(field_type, Exposure::Field, v),
         ItemEnum::AssocType {
             generics,
             bounds,
@@ -28,14 +88,14 @@ pub fn visit_item(item: &Item, v: &mut impl Visitor) {
         } => {
             visit_generics(generics, v);
             for bound in bounds {
-                visit_generic_bound(bound, v);
+                visit_generic_bound(bound, Exposure::Bound, v);
             }
             if let Some(default) = default {
-                visit_type(default, v);
+                visit_type(default, Exposure::AssocType, v);
             }
         }
         ItemEnum::AssocConst { type_, default: _ } => {
-            visit_type(type_, v);
+            visit_type(type_, Exposure::Type, v);
         }
         ItemEnum::Impl(impl_) => visit_impl(impl_, v),
         ItemEnum::TypeAlias(type_alias) => visit_type_alias(type_alias, v),
@@ -45,20 +105,20 @@ pub fn visit_item(item: &Item, v: &mut impl Visitor) {
         ItemEnum::Trait(trait_) => visit_trait(trait_, v),
         ItemEnum::TraitAlias(trait_alias) => visit_trait_alias(trait_alias, v),
         ItemEnum::OpaqueTy(opaque_type) => visit_opaque_type(opaque_type, v),
-        ItemEnum::Constant(constant) => visit_constant(constant, v),
+        ItemEnum::Constant(constant) => visit_constant(constant, Exposure::Type, v),
         ItemEnum::Static(static_) => visit_static(static_, v),
         ItemEnum::Import(import) => {
             v.visit_import(import);
         }
+        ItemEnum::ExternCrate { name, rename: _ } => v.visit_extern_crate(name),
+        ItemEnum::Macro(source) => v.visit_macro(source, item.span.as_ref()),
 
         // ignore these because they don't contain anything of interest
         ItemEnum::Module(_) => {}
         ItemEnum::Variant(_) => {}
-        ItemEnum::ExternCrate { .. } => {}
         ItemEnum::ForeignType => {}
         ItemEnum::Primitive(_) => {}
         ItemEnum::ProcMacro(_) => {}
-        ItemEnum::Macro(_) => {}
     }
 }
 
@@ -68,13 +128,13 @@ fn visit_static(static_: &Static, v: &mut impl Visitor) {
         mutable: _,
         expr: _,
     } = static_;
-    visit_type(type_, v);
+    visit_type(type_, Exposure::Type, v);
 }
 
 fn visit_opaque_type(opaque_type: &OpaqueTy, v: &mut impl Visitor) {
     let OpaqueTy { bounds, generics } = opaque_type;
     for bound in bounds {
-        visit_generic_bound(bound, v);
+        visit_generic_bound(bound, Exposure::Bound, v);
     }
     visit_generics(generics, v);
 }
@@ -83,7 +143,7 @@ fn visit_trait_alias(trait_alias: &TraitAlias, v: &mut impl Visitor) {
     let TraitAlias { generics, params } = trait_alias;
     visit_generics(generics, v);
     for param in params {
-        visit_generic_bound(param, v);
+        visit_generic_bound(param, Exposure::Supertrait, v);
     }
 }
 
@@ -98,7 +158,7 @@ fn visit_trait(trait_: &Trait, v: &mut impl Visitor) {
     } = trait_;
     visit_generics(generics, v);
     for bound in bounds {
-        visit_generic_bound(bound, v);
+        visit_generic_bound(bound, Exposure::Supertrait, v);
     }
 }
 
@@ -140,14 +200,14 @@ fn visit_impl(impl_: &Impl, v: &mut impl Visitor) {
     }
     visit_generics(generics, v);
     if let Some(trait_) = trait_ {
-        visit_path(trait_, v);
+        visit_path(trait_, Exposure::ImplTrait, v);
     }
-    visit_type(for_, v);
+    visit_type(for_, Exposure::ImplTarget, v);
 }
 
 fn visit_type_alias(type_alias: &TypeAlias, v: &mut impl Visitor) {
     let TypeAlias { type_, generics } = type_alias;
-    visit_type(type_, v);
+    visit_type(type_, Exposure::Type, v);
     visit_generics(generics, v);
 }
 
@@ -179,21 +239,31 @@ fn visit_function(fun: &Function, v: &mut impl Visitor) {
         header: _,
         has_body: _,
     } = fun;
-    visit_fn_decl(decl, v);
+    let FnDecl {
+        inputs,
+        output,
+        c_variadic: _,
+    } = decl;
+    for (_, ty) in inputs {
+        visit_type(ty, Exposure::Argument, v);
+    }
+    if let Some(output) = output {
+        visit_type(output, Exposure::ReturnType, v);
+    }
     visit_generics(generics, v);
 }
 
-fn visit_fn_decl(decl: &FnDecl, v: &mut impl Visitor) {
+fn visit_fn_decl(decl: &FnDecl, exposure: Exposure, v: &mut impl Visitor) {
     let FnDecl {
         inputs,
         output,
         c_variadic: _,
     } = decl;
     for (_, ty) in inputs {
-        visit_type(ty, v);
+        visit_type(ty, exposure, v);
     }
     if let Some(output) = output {
-        visit_type(output, v);
+        visit_type(output, exposure, v);
     }
 }
 
@@ -203,31 +273,35 @@ fn visit_generics(generics: &Generics, v: &mut impl Visitor) {
         where_predicates,
     } = generics;
     for param in params {
-        visit_generic_param_def(param, v);
+        visit_generic_param_def(param, Exposure::Bound, v);
     }
     for where_predicate in where_predicates {
-        visit_where_predicate(where_predicate, v);
+        visit_where_predicate(where_predicate, Exposure::WhereClause, v);
     }
 }
 
-fn visit_generic_param_def(param: &GenericParamDef, v: &mut impl Visitor) {
+fn visit_generic_param_def(param: &GenericParamDef, exposure: Exposure, v: &mut impl Visitor) {
     let GenericParamDef { name: _, kind } = param;
-    visit_generic_param_def_kind(kind, v);
+    visit_generic_param_def_kind(kind, exposure, v);
 }
 
-fn visit_where_predicate(where_predicate: &WherePredicate, v: &mut impl Visitor) {
+fn visit_where_predicate(
+    where_predicate: &WherePredicate,
+    exposure: Exposure,
+    v: &mut impl Visitor,
+) {
     match where_predicate {
         WherePredicate::BoundPredicate {
             type_,
             bounds,
             generic_params,
         } => {
-            visit_type(type_, v);
+            visit_type(type_, exposure, v);
             for bound in bounds {
-                visit_generic_bound(bound, v);
+                visit_generic_bound(bound, exposure, v);
             }
             for generic_param in generic_params {
-                visit_generic_param_def(generic_param, v);
+                visit_generic_param_def(generic_param, exposure, v);
             }
         }
         WherePredicate::RegionPredicate {
@@ -235,17 +309,21 @@ fn visit_where_predicate(where_predicate: &WherePredicate, v: &mut impl Visitor)
             bounds,
         } => {
             for bound in bounds {
-                visit_generic_bound(bound, v);
+                visit_generic_bound(bound, exposure, v);
             }
         }
         WherePredicate::EqPredicate { lhs, rhs } => {
-            visit_type(lhs, v);
-            visit_term(rhs, v);
+            visit_type(lhs, exposure, v);
+            visit_term(rhs, exposure, v);
         }
     }
 }
 
-fn visit_generic_param_def_kind(kind: &GenericParamDefKind, v: &mut impl Visitor) {
+fn visit_generic_param_def_kind(
+    kind: &GenericParamDefKind,
+    exposure: Exposure,
+    v: &mut impl Visitor,
+) {
     match kind {
         GenericParamDefKind::Lifetime { outlives: _ } => {}
         GenericParamDefKind::Type {
@@ -254,184 +332,184 @@ fn visit_generic_param_def_kind(kind: &GenericParamDefKind, v: &mut impl Visitor
             synthetic: _,
         } => {
             for bound in bounds {
-                visit_generic_bound(bound, v);
+                visit_generic_bound(bound, exposure, v);
             }
             if let Some(default) = default {
-                visit_type(default, v);
+                visit_type(default, exposure, v);
             }
         }
         GenericParamDefKind::Const { type_, default: _ } => {
-            visit_type(type_, v);
+            visit_type(type_, exposure, v);
         }
     }
 }
 
-fn visit_generic_bound(bound: &GenericBound, v: &mut impl Visitor) {
+fn visit_generic_bound(bound: &GenericBound, exposure: Exposure, v: &mut impl Visitor) {
     match bound {
         GenericBound::TraitBound {
             trait_,
             generic_params,
             modifier: _,
         } => {
-            visit_path(trait_, v);
+            visit_path(trait_, exposure, v);
             for param in generic_params {
-                visit_generic_param_def(param, v);
+                visit_generic_param_def(param, exposure, v);
             }
         }
         GenericBound::Outlives(_) => {}
     }
 }
 
-fn visit_term(term: &Term, v: &mut impl Visitor) {
+fn visit_term(term: &Term, exposure: Exposure, v: &mut impl Visitor) {
     match term {
-        Term::Type(type_) => visit_type(type_, v),
-        Term::Constant(constant) => visit_constant(constant, v),
+        Term::Type(type_) => visit_type(type_, exposure, v),
+        Term::Constant(constant) => visit_constant(constant, exposure, v),
     }
 }
 
-fn visit_path(path: &Path, v: &mut impl Visitor) {
-    v.visit_path(path);
+fn visit_path(path: &Path, exposure: Exposure, v: &mut impl Visitor) {
+    v.visit_path(path, exposure);
     let Path {
         name: _,
         id: _,
         args,
     } = path;
     if let Some(args) = args {
-        visit_generic_args(args, v);
+        visit_generic_args(args, exposure, v);
     }
 }
 
-fn visit_generic_args(args: &GenericArgs, v: &mut impl Visitor) {
+fn visit_generic_args(args: &GenericArgs, exposure: Exposure, v: &mut impl Visitor) {
     match args {
         GenericArgs::AngleBracketed { args, bindings } => {
             for arg in args {
-                visit_generic_arg(arg, v);
+                visit_generic_arg(arg, exposure, v);
             }
             for binding in bindings {
-                visit_type_binding(binding, v);
+                visit_type_binding(binding, exposure, v);
             }
         }
         GenericArgs::Parenthesized { inputs, output } => {
             for type_ in inputs {
-                visit_type(type_, v);
+                visit_type(type_, exposure, v);
             }
             if let Some(type_) = output {
-                visit_type(type_, v);
+                visit_type(type_, exposure, v);
             }
         }
     }
 }
 
-fn visit_type_binding(binding: &TypeBinding, v: &mut impl Visitor) {
+fn visit_type_binding(binding: &TypeBinding, exposure: Exposure, v: &mut impl Visitor) {
     let TypeBinding {
         name: _,
         args,
         binding,
     } = binding;
-    visit_generic_args(args, v);
-    visit_type_binding_kind(binding, v);
+    visit_generic_args(args, exposure, v);
+    visit_type_binding_kind(binding, exposure, v);
 }
 
-fn visit_type_binding_kind(binding: &TypeBindingKind, v: &mut impl Visitor) {
+fn visit_type_binding_kind(binding: &TypeBindingKind, exposure: Exposure, v: &mut impl Visitor) {
     match binding {
-        TypeBindingKind::Equality(term) => visit_term(term, v),
+        TypeBindingKind::Equality(term) => visit_term(term, exposure, v),
         TypeBindingKind::Constraint(bounds) => {
             for bound in bounds {
-                visit_generic_bound(bound, v)
+                visit_generic_bound(bound, exposure, v)
             }
         }
     }
 }
 
-fn visit_generic_arg(arg: &GenericArg, v: &mut impl Visitor) {
+fn visit_generic_arg(arg: &GenericArg, exposure: Exposure, v: &mut impl Visitor) {
     match arg {
         GenericArg::Lifetime(_) => {}
-        GenericArg::Type(type_) => visit_type(type_, v),
-        GenericArg::Const(constant) => visit_constant(constant, v),
+        GenericArg::Type(type_) => visit_type(type_, exposure, v),
+        GenericArg::Const(constant) => visit_constant(constant, exposure, v),
         GenericArg::Infer => {}
     }
 }
 
-fn visit_constant(constant: &Constant, v: &mut impl Visitor) {
+fn visit_constant(constant: &Constant, exposure: Exposure, v: &mut impl Visitor) {
     let Constant {
         type_,
         expr: _,
         value: _,
         is_literal: _,
     } = constant;
-    visit_type(type_, v);
+    visit_type(type_, exposure, v);
 }
 
-fn visit_type(type_: &Type, v: &mut impl Visitor) {
+fn visit_type(type_: &Type, exposure: Exposure, v: &mut impl Visitor) {
     match type_ {
-        Type::ResolvedPath(path) => visit_path(path, v),
-        Type::DynTrait(dyn_trait) => visit_dyn_trait(dyn_trait, v),
+        Type::ResolvedPath(path) => visit_path(path, exposure, v),
+        Type::DynTrait(dyn_trait) => visit_dyn_trait(dyn_trait, exposure, v),
         Type::Generic(_) => {}
         Type::Primitive(_) => {}
-        Type::FunctionPointer(fn_pointer) => visit_function_pointer(fn_pointer, v),
+        Type::FunctionPointer(fn_pointer) => visit_function_pointer(fn_pointer, exposure, v),
         Type::Tuple(types) => {
             for type_ in types {
-                visit_type(type_, v);
+                visit_type(type_, exposure, v);
             }
         }
-        Type::Slice(type_) => visit_type(type_, v),
-        Type::Array { type_, len: _ } => visit_type(type_, v),
+        Type::Slice(type_) => visit_type(type_, exposure, v),
+        Type::Array { type_, len: _ } => visit_type(type_, exposure, v),
         Type::ImplTrait(bounds) => {
             for bound in bounds {
-                visit_generic_bound(bound, v);
+                visit_generic_bound(bound, exposure, v);
             }
         }
         Type::Infer => {}
-        Type::RawPointer { mutable: _, type_ } => visit_type(type_, v),
+        Type::RawPointer { mutable: _, type_ } => visit_type(type_, exposure, v),
         Type::BorrowedRef {
             lifetime: _,
             mutable: _,
             type_,
-        } => visit_type(type_, v),
+        } => visit_type(type_, exposure, v),
         Type::QualifiedPath {
             name: _,
             args,
             self_type,
             trait_,
         } => {
-            visit_generic_args(args, v);
-            visit_type(self_type, v);
+            visit_generic_args(args, exposure, v);
+            visit_type(self_type, exposure, v);
             if let Some(trait_) = trait_ {
-                visit_path(trait_, v);
+                visit_path(trait_, exposure, v);
             }
         }
     }
 }
 
-fn visit_function_pointer(fn_pointer: &FunctionPointer, v: &mut impl Visitor) {
+fn visit_function_pointer(fn_pointer: &FunctionPointer, exposure: Exposure, v: &mut impl Visitor) {
     let FunctionPointer {
         decl,
         generic_params,
         header: _,
     } = fn_pointer;
-    visit_fn_decl(decl, v);
+    visit_fn_decl(decl, exposure, v);
     for generic_param in generic_params {
-        visit_generic_param_def(generic_param, v);
+        visit_generic_param_def(generic_param, exposure, v);
     }
 }
 
-fn visit_dyn_trait(dyn_trait: &DynTrait, v: &mut impl Visitor) {
+fn visit_dyn_trait(dyn_trait: &DynTrait, exposure: Exposure, v: &mut impl Visitor) {
     let DynTrait {
         traits,
         lifetime: _,
     } = dyn_trait;
     for trait_ in traits {
-        visit_poly_trait(trait_, v);
+        visit_poly_trait(trait_, exposure, v);
     }
 }
 
-fn visit_poly_trait(trait_: &PolyTrait, v: &mut impl Visitor) {
+fn visit_poly_trait(trait_: &PolyTrait, exposure: Exposure, v: &mut impl Visitor) {
     let PolyTrait {
         trait_,
         generic_params,
     } = trait_;
-    visit_path(trait_, v);
+    visit_path(trait_, exposure, v);
     for generic_param in generic_params {
-        visit_generic_param_def(generic_param, v);
+        visit_generic_param_def(generic_param, exposure, v);
     }
 }
@@ -0,0 +1,145 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    build_docs::Toolchain,
+    dependencies::{self, Dependency},
+    metadata::{self, Metadata, Package, ToolMetadata},
+};
+
+/// A library package whose public API is analyzed.
+#[derive(Debug, Clone)]
+pub struct Member {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    /// Name of the library target, which is also the name of the rustdoc JSON file.
+    pub lib_name: String,
+    pub target_directory: PathBuf,
+    /// Features declared by the package, not including `default`.
+    pub features: Vec<String>,
+    /// Packages the member depends on, directly or indirectly.
+    pub dependencies: Vec<Dependency>,
+    /// Toolchain to build the docs with.
+    pub toolchain: Toolchain,
+}
+
+impl Member {
+    /// The directory containing the package's Cargo.toml.
+    pub fn manifest_dir(&self) -> &Path {
+        self.manifest_path.parent().unwrap_or(Path::new(""))
+    }
+}
+
+/// Select the packages to analyze.
+///
+/// Without `--workspace` or `-p` this is the package at `manifest_path`, which must not be a
+/// virtual manifest.
+pub fn members(
+    manifest_path: Option<&Path>,
+    workspace: bool,
+    packages: &[String],
+) -> Result<Vec<Member>> {
+    let metadata = metadata::run(manifest_path)?;
+
+    let workspace_members = metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace_members.contains(&package.id))
+        .collect::<Vec<_>>();
+
+    if workspace {
+        // `-p` narrows down the workspace, a name that isn't in it is most likely a typo
+        let is_member = |name: &String| {
+            workspace_members
+                .iter()
+                .any(|package| &package.name == name)
+        };
+        if let Some(name) = packages.iter().find(|name| !is_member(name)) {
+            bail!("package `{name}` not found in workspace");
+        }
+        let members = workspace_members
+            .into_iter()
+            .filter(|package| packages.is_empty() || packages.contains(&package.name))
+            .filter_map(|package| member(package, &metadata))
+            .collect::<Vec<_>>();
+        if members.is_empty() {
+            bail!("no library packages found in workspace");
+        }
+        return Ok(members);
+    }
+
+    if !packages.is_empty() {
+        return packages
+            .iter()
+            .map(|name| {
+                let package = workspace_members
+                    .iter()
+                    .find(|package| &package.name == name)
+                    .with_context(|| format!("package `{name}` not found in workspace"))?;
+                member(package, &metadata)
+                    .with_context(|| format!("package `{name}` doesn't have a library target"))
+            })
+            .collect();
+    }
+
+    let manifest_path = manifest_path.unwrap_or_else(|| Path::new("Cargo.toml"));
+    let manifest_path = manifest_path
+        .canonicalize()
+        .with_context(|| format!("failed to read {}", manifest_path.display()))?;
+
+    let package = workspace_members
+        .into_iter()
+        .find(|package| package.manifest_path == manifest_path)
+        .with_context(|| {
+            format!(
+                "{} is a virtual manifest, use `--workspace` or `-p <package>` to select packages",
+                manifest_path.display()
+            )
+        })?;
+
+    let member = member(package, &metadata)
+        .with_context(|| format!("package `{}` doesn't have a library target", package.name))?;
+
+    Ok(vec![member])
+}
+
+fn member(package: &Package, metadata: &Metadata) -> Option<Member> {
+    let lib_target = package.lib_target()?;
+    Some(Member {
+        name: package.name.clone(),
+        manifest_path: package.manifest_path.clone(),
+        lib_name: lib_target.name.clone(),
+        target_directory: metadata.target_directory.clone(),
+        features: package
+            .features
+            .keys()
+            .filter(|feature| *feature != "default")
+            .cloned()
+            .collect(),
+        dependencies: dependencies::closure(metadata, &package.id),
+        toolchain: toolchain(package, metadata),
+    })
+}
+
+/// `$CARGO_PUBLIC_API_CRATES_TOOLCHAIN`, or `toolchain` in the package's or workspace's
+/// `[*.metadata.cargo-public-api-crates]`, or `nightly`. `--toolchain` overrides all of them.
+fn toolchain(package: &Package, metadata: &Metadata) -> Toolchain {
+    let configured = |metadata: &Option<ToolMetadata>| {
+        metadata
+            .as_ref()?
+            .cargo_public_api_crates
+            .as_ref()?
+            .toolchain
+            .clone()
+    };
+    env::var("CARGO_PUBLIC_API_CRATES_TOOLCHAIN")
+        .ok()
+        .or_else(|| configured(&package.metadata))
+        .or_else(|| configured(&metadata.metadata))
+        .map_or_else(Toolchain::default, |toolchain| {
+            Toolchain::from(toolchain.as_str())
+        })
+}